@@ -1,16 +1,16 @@
+use std::any::{type_name, Any, TypeId};
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
 use gtk4::glib;
 use crate::bus::events::inter::event::Event;
-use crate::utils::random;
+use crate::bus::queries::inter::query::Query;
+use crate::bus::subscription::Subscription;
+use crate::utils::clock;
 
-#[cfg(feature = "gtk3")]
-use gtk::glib;
-
-#[cfg(feature = "gtk4")]
-use gtk4::glib;
-
-type EventCallback = Box<dyn Fn(u32, &Box<dyn Event>) -> EventPropagation>;
+type EventCallback = Rc<dyn Fn(u32, &Box<dyn Event>) -> EventPropagation>;
 
 #[derive(Clone, Copy, Eq, PartialEq, Debug)]
 pub enum EventPropagation {
@@ -18,20 +18,53 @@ pub enum EventPropagation {
     Stop
 }
 
+type EventFilter = Rc<dyn Fn(&Box<dyn Event>) -> bool>;
+
+type EventMiddleware = Rc<dyn Fn(&mut Box<dyn Event>) -> EventPropagation>;
+
+type QueryResponder = Rc<dyn Fn(&dyn Any) -> Box<dyn Any>>;
+
 struct CallbackState {
     callback: EventCallback,
+    filter: Option<EventFilter>,
     paused: bool,
 }
 
+struct ResponderState {
+    id: u32,
+    responder: QueryResponder
+}
+
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+
 thread_local! {
     static EVENT_BUS: RefCell<HashMap<String, HashMap<u32, CallbackState>>> = RefCell::new(HashMap::new());
+    static QUERY_BUS: RefCell<HashMap<TypeId, ResponderState>> = RefCell::new(HashMap::new());
+    static STICKY_EVENTS: RefCell<HashMap<String, Box<dyn Event>>> = RefCell::new(HashMap::new());
+    static MIDDLEWARE: RefCell<Vec<(u32, EventMiddleware)>> = RefCell::new(Vec::new());
 }
 
-pub fn register_event<F>(event: &str, callback: F, paused: bool) -> u32
+fn next_id() -> u32 {
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+pub fn register_event<F>(event: &str, callback: F, paused: bool) -> Subscription
 where
     F: Fn(u32, &Box<dyn Event>) -> EventPropagation + 'static,
 {
-    let callback_id = random::r#gen::<u32>();
+    insert_callback(event, Rc::new(callback), None, paused)
+}
+
+pub fn register_event_filtered<P, F>(event: &str, filter: P, callback: F, paused: bool) -> Subscription
+where
+    P: Fn(&Box<dyn Event>) -> bool + 'static,
+    F: Fn(u32, &Box<dyn Event>) -> EventPropagation + 'static,
+{
+    insert_callback(event, Rc::new(callback), Some(Rc::new(filter)), paused)
+}
+
+fn insert_callback(event: &str, callback: EventCallback, filter: Option<EventFilter>, paused: bool) -> Subscription {
+    let callback_id = next_id();
 
     EVENT_BUS.with(|subs| {
         let mut subs = subs.borrow_mut();
@@ -40,25 +73,37 @@ where
             .insert(
                 callback_id,
                 CallbackState {
-                    callback: Box::new(callback),
+                    callback: callback.clone(),
+                    filter: filter.clone(),
                     paused
                 }
             );
     });
 
-    callback_id
+    if !paused {
+        for sticky in get_sticky_events_matching(event) {
+            if filter.as_ref().is_some_and(|filter| !filter(&sticky)) {
+                continue;
+            }
+
+            if callback(callback_id, &sticky).eq(&EventPropagation::Stop) {
+                unregister_event(event, callback_id);
+                break;
+            }
+        }
+    }
+
+    Subscription::event(event, callback_id)
 }
 
 pub fn unregister_event(event: &str, callback_id: u32) -> bool {
     EVENT_BUS.with(|subs| {
         let mut subs = subs.borrow_mut();
-        if let Some(callbacks) = subs.get_mut(event) {
-            if callbacks.remove(&callback_id).is_some() {
-                if callbacks.is_empty() {
-                    subs.remove(event);
-                }
-                return true;
+        if let Some(callbacks) = subs.get_mut(event) && callbacks.remove(&callback_id).is_some() {
+            if callbacks.is_empty() {
+                subs.remove(event);
             }
+            return true;
         }
         false
     })
@@ -67,11 +112,9 @@ pub fn unregister_event(event: &str, callback_id: u32) -> bool {
 pub fn pause_event(event: &str, callback_id: u32) -> bool {
     EVENT_BUS.with(|subs| {
         let mut subs = subs.borrow_mut();
-        if let Some(callbacks) = subs.get_mut(event) {
-            if let Some(callback_state) = callbacks.get_mut(&callback_id) {
-                callback_state.paused = true;
-                return true;
-            }
+        if let Some(callbacks) = subs.get_mut(event) && let Some(callback_state) = callbacks.get_mut(&callback_id) {
+            callback_state.paused = true;
+            return true;
         }
         false
     })
@@ -80,35 +123,416 @@ pub fn pause_event(event: &str, callback_id: u32) -> bool {
 pub fn resume_event(event: &str, callback_id: u32) -> bool {
     EVENT_BUS.with(|subs| {
         let mut subs = subs.borrow_mut();
-        if let Some(callbacks) = subs.get_mut(event) {
-            if let Some(callback_state) = callbacks.get_mut(&callback_id) {
-                callback_state.paused = false;
-                return true;
-            }
+        if let Some(callbacks) = subs.get_mut(event) && let Some(callback_state) = callbacks.get_mut(&callback_id) {
+            callback_state.paused = false;
+            return true;
         }
         false
     })
 }
 
+pub fn get_sticky_event(event: &str) -> Option<Box<dyn Event>> {
+    STICKY_EVENTS.with(|sticky| {
+        sticky.borrow().get(event).map(|data| data.dyn_clone())
+    })
+}
+
+fn get_sticky_events_matching(pattern: &str) -> Vec<Box<dyn Event>> {
+    STICKY_EVENTS.with(|sticky| {
+        sticky.borrow().iter()
+            .filter(|(name, _)| matches_event(pattern, name))
+            .map(|(_, data)| data.dyn_clone())
+            .collect()
+    })
+}
+
+pub fn clear_sticky_event(event: &str) -> bool {
+    STICKY_EVENTS.with(|sticky| {
+        sticky.borrow_mut().remove(event).is_some()
+    })
+}
+
+pub fn register_middleware<F>(middleware: F) -> Subscription
+where
+    F: Fn(&mut Box<dyn Event>) -> EventPropagation + 'static,
+{
+    let middleware_id = next_id();
+
+    MIDDLEWARE.with(|middlewares| {
+        middlewares.borrow_mut().push((middleware_id, Rc::new(middleware)));
+    });
+
+    Subscription::middleware(middleware_id)
+}
+
+pub fn unregister_middleware(middleware_id: u32) -> bool {
+    MIDDLEWARE.with(|middlewares| {
+        let mut middlewares = middlewares.borrow_mut();
+        let len = middlewares.len();
+        middlewares.retain(|(id, _)| *id != middleware_id);
+        middlewares.len() != len
+    })
+}
+
+pub fn register_query<Q, F>(responder: F) -> Subscription
+where
+    Q: Query,
+    F: Fn(&Q) -> Q::Response + 'static,
+{
+    let responder_id = next_id();
+
+    QUERY_BUS.with(|responders| {
+        let mut responders = responders.borrow_mut();
+
+        // One responder per query, the first keeps answering and the second gets a subscription that holds nothing
+        if responders.contains_key(&TypeId::of::<Q>()) {
+            eprintln!("Error: {} already has a responder, keeping the first", type_name::<Q>());
+            return;
+        }
+
+        responders.insert(
+            TypeId::of::<Q>(),
+            ResponderState {
+                id: responder_id,
+                responder: Rc::new(move |query: &dyn Any| {
+                    Box::new(responder(query.downcast_ref::<Q>().unwrap())) as Box<dyn Any>
+                })
+            }
+        );
+    });
+
+    Subscription::query(TypeId::of::<Q>(), responder_id)
+}
+
+pub(crate) fn remove_responder(type_id: TypeId, responder_id: u32) -> bool {
+    QUERY_BUS.with(|responders| {
+        let mut responders = responders.borrow_mut();
+        match responders.get(&type_id) {
+            Some(state) if state.id == responder_id => {
+                responders.remove(&type_id);
+                true
+            }
+            _ => false
+        }
+    })
+}
+
+pub fn query<Q: Query>(query: Q) -> Option<Q::Response> {
+    let responder = QUERY_BUS.with(|responders| {
+        responders.borrow().get(&TypeId::of::<Q>()).map(|state| state.responder.clone())
+    })?;
+
+    responder(&query).downcast::<Q::Response>().ok().map(|response| *response)
+}
+
 pub fn send_event(data: Box<dyn Event>) {
     glib::MainContext::default().invoke(move || {
-        EVENT_BUS.with(|subs| {
-            let mut subs = subs.borrow_mut();
-            if let Some(callbacks) = subs.get_mut(&data.get_name()) {
-                let mut keys_to_remove = Vec::new();
-
-                for (key, callback_state) in callbacks.iter() {
-                    if !callback_state.paused {
-                        if !(callback_state.callback)(*key, &data).eq(&EventPropagation::Continue) {
-                            keys_to_remove.push(key.clone());
-                        }
-                    }
-                }
+        dispatch_event(data);
+    });
+}
 
-                for key in keys_to_remove {
-                    callbacks.remove(&key);
-                }
+// Owns a scheduled send, which stops when the handle is cancelled or dropped like a Subscription
+#[derive(Debug)]
+pub struct ScheduleHandle {
+    // Empty once a one-off send has fired or the schedule was cancelled
+    source: Rc<RefCell<Option<glib::SourceId>>>
+}
+
+impl ScheduleHandle {
+
+    pub fn cancel(&self) -> bool {
+        match self.source.borrow_mut().take() {
+            Some(source) => {
+                source.remove();
+                true
             }
+            None => false
+        }
+    }
+}
+
+impl Drop for ScheduleHandle {
+
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+pub fn send_event_after(delay: Duration, data: Box<dyn Event>) -> ScheduleHandle {
+    let source = Rc::new(RefCell::new(None));
+
+    let id = glib::timeout_add_local_once(delay, {
+        let source = Rc::clone(&source);
+        move || {
+            source.borrow_mut().take();
+            dispatch_event(data);
+        }
+    });
+    *source.borrow_mut() = Some(id);
+
+    ScheduleHandle {
+        source
+    }
+}
+
+// Measured on the injectable clock, so an instant in the past sends on the next loop iteration
+pub fn send_event_at(when: Instant, data: Box<dyn Event>) -> ScheduleHandle {
+    send_event_after(when.saturating_duration_since(clock::now()), data)
+}
+
+pub fn send_event_every_after<F>(delay: Duration, interval: Duration, factory: F) -> ScheduleHandle
+where
+    F: Fn() -> Box<dyn Event> + 'static,
+{
+    let source = Rc::new(RefCell::new(None));
+    let factory = Rc::new(factory);
+
+    let id = glib::timeout_add_local_once(delay, {
+        let source = Rc::clone(&source);
+        move || {
+            // In place of the spent first source before the first send, so a subscriber can cancel from it
+            let repeating = glib::timeout_add_local(interval, {
+                let factory = Rc::clone(&factory);
+                move || {
+                    dispatch_event(factory());
+                    glib::ControlFlow::Continue
+                }
+            });
+            *source.borrow_mut() = Some(repeating);
+
+            dispatch_event(factory());
+        }
+    });
+    *source.borrow_mut() = Some(id);
+
+    ScheduleHandle {
+        source
+    }
+}
+
+fn dispatch_event(mut data: Box<dyn Event>) {
+    let middlewares: Vec<EventMiddleware> = MIDDLEWARE.with(|middlewares| {
+        middlewares.borrow().iter().map(|(_, middleware)| middleware.clone()).collect()
+    });
+
+    for middleware in middlewares {
+        if middleware(&mut data).eq(&EventPropagation::Stop) {
+            return;
+        }
+    }
+
+    let name = data.get_name();
+
+    if data.is_sticky() {
+        STICKY_EVENTS.with(|sticky| {
+            sticky.borrow_mut().insert(name.clone(), data.dyn_clone());
         });
+    }
+
+    let callbacks: Vec<(String, u32, EventCallback, Option<EventFilter>)> = EVENT_BUS.with(|subs| {
+        subs.borrow().iter()
+            .filter(|(pattern, _)| matches_event(pattern, &name))
+            .flat_map(|(pattern, callbacks)| {
+                callbacks.iter()
+                    .filter(|(_, callback_state)| !callback_state.paused)
+                    .map(move |(key, callback_state)| (pattern.clone(), *key, callback_state.callback.clone(), callback_state.filter.clone()))
+            })
+            .collect()
     });
+
+    for (pattern, key, callback, filter) in callbacks {
+        if filter.is_some_and(|filter| !filter(&data)) {
+            continue;
+        }
+
+        if !callback(key, &data).eq(&EventPropagation::Continue) {
+            unregister_event(&pattern, key);
+        }
+    }
+}
+
+fn matches_event(pattern: &str, name: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => pattern == name
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::cell::Cell;
+    use std::sync::{Arc, Mutex, MutexGuard};
+    use super::*;
+    use crate::bus::events::obambo_event::ObamboEvent;
+    use crate::bus::events::speed_event::SpeedEvent;
+    use crate::bus::queries::speed_query::SpeedQuery;
+    use crate::utils::clock::FakeClock;
+
+    // Tests that dispatch through the default main context or swap the shared settings take turns
+    pub(crate) fn test_lock() -> MutexGuard<'static, ()> {
+        static LOCK: Mutex<()> = Mutex::new(());
+        LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn count_obambos() -> (Rc<Cell<u32>>, Subscription) {
+        let count = Rc::new(Cell::new(0));
+        let subscription = register_event("obambo_event", {
+            let count = Rc::clone(&count);
+            move |_, _| {
+                count.set(count.get() + 1);
+                EventPropagation::Continue
+            }
+        }, false);
+        (count, subscription)
+    }
+
+    // Runs the default main context for real time, the timeouts behind the schedules don't read the fake clock
+    fn run_for(duration: Duration) {
+        let context = glib::MainContext::default();
+        let deadline = Instant::now() + duration;
+        while Instant::now() < deadline {
+            context.iteration(false);
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    fn run_until(done: impl Fn() -> bool) {
+        let context = glib::MainContext::default();
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while !done() && Instant::now() < deadline {
+            context.iteration(false);
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn a_second_responder_leaves_the_first_in_place() {
+        let _serial = test_lock();
+
+        let first = register_query::<SpeedQuery, _>(|_| Some(1.0));
+        let second = register_query::<SpeedQuery, _>(|_| Some(2.0));
+        assert_eq!(query(SpeedQuery::new()), Some(Some(1.0)));
+
+        // Dropping the rejected one doesn't take the first down with it
+        assert!(!second.unsubscribe());
+        assert_eq!(query(SpeedQuery::new()), Some(Some(1.0)));
+
+        drop(first);
+        assert_eq!(query(SpeedQuery::new()), None);
+    }
+
+    #[test]
+    fn sends_after_the_delay() {
+        let _serial = test_lock();
+        let (count, _subscription) = count_obambos();
+
+        let scheduled = Instant::now();
+        let _handle = send_event_after(Duration::from_millis(30), Box::new(ObamboEvent::new()));
+        assert_eq!(count.get(), 0);
+
+        run_until(|| count.get() > 0);
+        assert_eq!(count.get(), 1);
+        assert!(scheduled.elapsed() >= Duration::from_millis(30));
+    }
+
+    #[test]
+    fn sends_at_an_instant_on_the_clock() {
+        let _serial = test_lock();
+        let (count, _subscription) = count_obambos();
+
+        let fake = Arc::new(FakeClock::default());
+        clock::set_clock(fake.clone());
+
+        let far = send_event_at(clock::now() + Duration::from_secs(60), Box::new(ObamboEvent::new()));
+        let passed = clock::now();
+        fake.advance(Duration::from_secs(1));
+        let _past = send_event_at(passed, Box::new(ObamboEvent::new()));
+
+        run_until(|| count.get() > 0);
+        run_for(Duration::from_millis(20));
+        clock::reset_clock();
+
+        // Only the instant that already passed went out
+        assert_eq!(count.get(), 1);
+        assert!(far.cancel());
+    }
+
+    #[test]
+    fn repeats_until_cancelled() {
+        let _serial = test_lock();
+        let (count, _subscription) = count_obambos();
+
+        let handle = send_event_every_after(Duration::from_millis(10), Duration::from_millis(10), || Box::new(ObamboEvent::new()));
+        run_until(|| count.get() >= 3);
+        assert!(handle.cancel());
+
+        let sent = count.get();
+        run_for(Duration::from_millis(50));
+        assert_eq!(count.get(), sent);
+    }
+
+    #[test]
+    fn cancelled_and_dropped_schedules_never_send() {
+        let _serial = test_lock();
+        let (count, _subscription) = count_obambos();
+
+        let cancelled = send_event_after(Duration::from_millis(10), Box::new(ObamboEvent::new()));
+        assert!(cancelled.cancel());
+        assert!(!cancelled.cancel());
+
+        drop(send_event_every_after(Duration::from_millis(10), Duration::from_millis(10), || Box::new(ObamboEvent::new())));
+
+        run_for(Duration::from_millis(50));
+        assert_eq!(count.get(), 0);
+    }
+
+    #[test]
+    fn cancel_after_a_one_off_fired_is_a_no_op() {
+        let _serial = test_lock();
+        let (count, _subscription) = count_obambos();
+
+        let handle = send_event_after(Duration::ZERO, Box::new(ObamboEvent::new()));
+        run_until(|| count.get() > 0);
+        assert!(!handle.cancel());
+    }
+
+    fn collect_speeds(received: &Rc<RefCell<Vec<Option<f64>>>>) -> Subscription {
+        let received = Rc::clone(received);
+        register_event("speed_event", move |_, event| {
+            received.borrow_mut().push(event.as_any().downcast_ref::<SpeedEvent>().unwrap().speed);
+            EventPropagation::Continue
+        }, false)
+    }
+
+    #[test]
+    fn late_subscribers_get_the_last_sticky_value() {
+        let _serial = test_lock();
+
+        send_event(Box::new(SpeedEvent::new(Some(1.0))));
+        send_event(Box::new(SpeedEvent::new(Some(2.5))));
+
+        let received = Rc::new(RefCell::new(Vec::new()));
+        let _subscription = collect_speeds(&received);
+        assert_eq!(*received.borrow(), vec![Some(2.5)]);
+
+        send_event(Box::new(SpeedEvent::new(None)));
+        assert_eq!(*received.borrow(), vec![Some(2.5), None]);
+
+        let last = get_sticky_event("speed_event").unwrap();
+        assert_eq!(last.as_any().downcast_ref::<SpeedEvent>().unwrap().speed, None);
+    }
+
+    #[test]
+    fn cleared_sticky_events_are_not_replayed() {
+        let _serial = test_lock();
+
+        send_event(Box::new(SpeedEvent::new(Some(1.0))));
+        assert!(clear_sticky_event("speed_event"));
+        assert!(!clear_sticky_event("speed_event"));
+
+        let received = Rc::new(RefCell::new(Vec::new()));
+        let _subscription = collect_speeds(&received);
+        assert!(received.borrow().is_empty());
+        assert!(get_sticky_event("speed_event").is_none());
+    }
 }
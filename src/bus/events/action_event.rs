@@ -0,0 +1,40 @@
+use std::any::Any;
+use std::time::Instant;
+use crate::bus::events::button_event::InputSource;
+use crate::input::action::Action;
+use crate::bus::events::inter::event::Event;
+
+#[derive(Debug, Clone)]
+pub struct ActionEvent {
+    pub(crate) action: Action,
+    // Carried for subscribers that tell backends apart, none of the current ones do
+    #[allow(dead_code)]
+    pub(crate) source: InputSource,
+    pub(crate) time: Instant
+}
+
+impl ActionEvent {
+
+    pub fn new(action: Action, source: InputSource, time: Instant) -> Self {
+        Self {
+            action,
+            source,
+            time
+        }
+    }
+}
+
+impl Event for ActionEvent {
+
+    fn get_name(&self) -> String {
+        String::from("action_event")
+    }
+
+    fn dyn_clone(&self) -> Box<dyn Event> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
@@ -1,22 +1,50 @@
 use std::any::Any;
-use std::collections::HashMap;
-use rdev::Key;
+use std::time::Instant;
 use crate::bus::events::inter::event::Event;
+use crate::input::chord::Modifiers;
+use crate::input::inputs::Input;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ButtonState {
+    Pressed,
+    Released
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum InputSource {
+    Keyboard,
+    Mouse,
+    // No backend sends remote input yet
+    #[allow(dead_code)]
+    Remote,
+    #[cfg(target_os = "linux")]
+    Portal
+}
 
 #[derive(Debug, Clone)]
 pub struct ButtonEvent {
-    prevent_default: bool,
-    pub(crate) button: Key
+    pub(crate) button: Input,
+    pub(crate) modifiers: Modifiers,
+    pub(crate) state: ButtonState,
+    pub(crate) source: InputSource,
+    pub(crate) time: Instant
 }
 
 impl ButtonEvent {
 
-    pub fn new(button: Key) -> Self {
+    pub fn new(button: Input, modifiers: Modifiers, state: ButtonState, source: InputSource, time: Instant) -> Self {
         Self {
-            prevent_default: false,
-            button
+            button,
+            modifiers,
+            state,
+            source,
+            time
         }
     }
+
+    pub fn is_pressed(&self) -> bool {
+        self.state == ButtonState::Pressed
+    }
 }
 
 impl Event for ButtonEvent {
@@ -25,22 +53,6 @@ impl Event for ButtonEvent {
         String::from("button_event")
     }
 
-    fn is_prevent_default(&self) -> bool {
-        todo!()
-    }
-
-    fn prevent_default(&mut self) {
-        todo!()
-    }
-
-    fn upcast(&self) -> &dyn Event {
-        self
-    }
-
-    fn upcast_mut(&mut self) -> &mut dyn Event {
-        self
-    }
-
     fn dyn_clone(&self) -> Box<dyn Event> {
         Box::new(self.clone())
     }
@@ -48,8 +60,4 @@ impl Event for ButtonEvent {
     fn as_any(&self) -> &dyn Any {
         self
     }
-
-    fn as_any_mut(&mut self) -> &mut dyn Any {
-        self
-    }
 }
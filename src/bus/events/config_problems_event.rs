@@ -0,0 +1,39 @@
+use std::any::Any;
+use crate::settings::config::Problem;
+use crate::bus::events::inter::event::Event;
+
+#[derive(Debug, Clone)]
+pub struct ConfigProblemsEvent {
+    pub(crate) problems: Vec<Problem>,
+    // A hand edit that was ignored as a whole, rather than loaded with defaults in place of the problems
+    pub(crate) rejected: bool
+}
+
+impl ConfigProblemsEvent {
+
+    pub fn new(problems: Vec<Problem>, rejected: bool) -> Self {
+        Self {
+            problems,
+            rejected
+        }
+    }
+}
+
+impl Event for ConfigProblemsEvent {
+
+    fn get_name(&self) -> String {
+        String::from("config_problems_event")
+    }
+
+    fn is_sticky(&self) -> bool {
+        true
+    }
+
+    fn dyn_clone(&self) -> Box<dyn Event> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
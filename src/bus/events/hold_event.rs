@@ -0,0 +1,38 @@
+use std::any::Any;
+use std::time::Instant;
+use crate::bus::events::button_event::InputSource;
+use crate::input::action::Action;
+use crate::bus::events::inter::event::Event;
+
+#[derive(Debug, Clone)]
+pub struct HoldEvent {
+    pub(crate) action: Action,
+    pub(crate) source: InputSource,
+    pub(crate) time: Instant
+}
+
+impl HoldEvent {
+
+    pub fn new(action: Action, source: InputSource, time: Instant) -> Self {
+        Self {
+            action,
+            source,
+            time
+        }
+    }
+}
+
+impl Event for HoldEvent {
+
+    fn get_name(&self) -> String {
+        String::from("hold_event")
+    }
+
+    fn dyn_clone(&self) -> Box<dyn Event> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
@@ -0,0 +1,34 @@
+use std::any::Any;
+use std::time::{Duration, Instant};
+use crate::bus::events::inter::event::Event;
+
+#[derive(Debug, Clone)]
+pub struct HoldProgressEvent {
+    pub(crate) started: Option<Instant>,
+    pub(crate) duration: Duration
+}
+
+impl HoldProgressEvent {
+
+    pub fn new(started: Option<Instant>, duration: Duration) -> Self {
+        Self {
+            started,
+            duration
+        }
+    }
+}
+
+impl Event for HoldProgressEvent {
+
+    fn get_name(&self) -> String {
+        String::from("hold_progress_event")
+    }
+
+    fn dyn_clone(&self) -> Box<dyn Event> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
@@ -0,0 +1,35 @@
+use std::any::Any;
+use crate::bus::events::inter::event::Event;
+
+#[derive(Debug, Clone)]
+pub struct LockEvent {
+    pub(crate) locked: bool
+}
+
+impl LockEvent {
+
+    pub fn new(locked: bool) -> Self {
+        Self {
+            locked
+        }
+    }
+}
+
+impl Event for LockEvent {
+
+    fn get_name(&self) -> String {
+        String::from("lock_event")
+    }
+
+    fn is_sticky(&self) -> bool {
+        true
+    }
+
+    fn dyn_clone(&self) -> Box<dyn Event> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
@@ -1,3 +1,12 @@
 pub mod inter;
 pub mod button_event;
-pub mod timer_event;
+pub mod obambo_event;
+pub mod speed_event;
+pub mod timer_state_event;
+pub mod settings_event;
+pub mod action_event;
+pub mod hold_event;
+pub mod hold_progress_event;
+pub mod lock_event;
+pub mod config_problems_event;
+pub mod save_result_event;
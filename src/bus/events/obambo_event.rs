@@ -0,0 +1,27 @@
+use std::any::Any;
+use crate::bus::events::inter::event::Event;
+
+#[derive(Debug, Clone)]
+pub struct ObamboEvent;
+
+impl ObamboEvent {
+
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Event for ObamboEvent {
+
+    fn get_name(&self) -> String {
+        String::from("obambo_event")
+    }
+
+    fn dyn_clone(&self) -> Box<dyn Event> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
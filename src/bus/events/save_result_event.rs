@@ -0,0 +1,35 @@
+use std::any::Any;
+use crate::bus::events::inter::event::Event;
+
+#[derive(Debug, Clone)]
+pub struct SaveResultEvent {
+    pub(crate) error: Option<String>
+}
+
+impl SaveResultEvent {
+
+    pub fn new(error: Option<String>) -> Self {
+        Self {
+            error
+        }
+    }
+}
+
+impl Event for SaveResultEvent {
+
+    fn get_name(&self) -> String {
+        String::from("save_result_event")
+    }
+
+    fn is_sticky(&self) -> bool {
+        true
+    }
+
+    fn dyn_clone(&self) -> Box<dyn Event> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
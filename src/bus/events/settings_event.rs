@@ -0,0 +1,36 @@
+use std::any::Any;
+use crate::settings::Settings;
+use crate::bus::events::inter::event::Event;
+
+#[derive(Debug, Clone)]
+pub struct SettingsEvent {
+    pub(crate) settings: Settings
+}
+
+impl SettingsEvent {
+
+    pub fn new(settings: Settings) -> Self {
+        Self {
+            settings
+        }
+    }
+}
+
+impl Event for SettingsEvent {
+
+    fn get_name(&self) -> String {
+        String::from("settings_event")
+    }
+
+    fn is_sticky(&self) -> bool {
+        true
+    }
+
+    fn dyn_clone(&self) -> Box<dyn Event> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
@@ -0,0 +1,35 @@
+use std::any::Any;
+use crate::bus::events::inter::event::Event;
+
+#[derive(Debug, Clone)]
+pub struct SpeedEvent {
+    pub(crate) speed: Option<f64>
+}
+
+impl SpeedEvent {
+
+    pub fn new(speed: Option<f64>) -> Self {
+        Self {
+            speed
+        }
+    }
+}
+
+impl Event for SpeedEvent {
+
+    fn get_name(&self) -> String {
+        String::from("speed_event")
+    }
+
+    fn is_sticky(&self) -> bool {
+        true
+    }
+
+    fn dyn_clone(&self) -> Box<dyn Event> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
@@ -0,0 +1,38 @@
+use std::any::Any;
+use std::time::Instant;
+use crate::bus::events::inter::event::Event;
+
+#[derive(Debug, Clone)]
+pub struct TimerStateEvent {
+    pub(crate) smudge: Option<Instant>,
+    pub(crate) obambo: Option<Instant>
+}
+
+impl TimerStateEvent {
+
+    pub fn new(smudge: Option<Instant>, obambo: Option<Instant>) -> Self {
+        Self {
+            smudge,
+            obambo
+        }
+    }
+}
+
+impl Event for TimerStateEvent {
+
+    fn get_name(&self) -> String {
+        String::from("timer_state_event")
+    }
+
+    fn is_sticky(&self) -> bool {
+        true
+    }
+
+    fn dyn_clone(&self) -> Box<dyn Event> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
@@ -1,2 +1,4 @@
 pub mod events;
+pub mod queries;
+pub mod subscription;
 pub mod event_bus;
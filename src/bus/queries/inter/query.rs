@@ -0,0 +1,6 @@
+use std::any::Any;
+
+pub trait Query: Any {
+
+    type Response: 'static;
+}
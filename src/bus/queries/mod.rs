@@ -0,0 +1,3 @@
+pub mod inter;
+pub mod timer_query;
+pub mod speed_query;
@@ -0,0 +1,16 @@
+use crate::bus::queries::inter::query::Query;
+
+#[derive(Debug, Clone)]
+pub struct SpeedQuery;
+
+impl SpeedQuery {
+
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Query for SpeedQuery {
+
+    type Response = Option<f64>;
+}
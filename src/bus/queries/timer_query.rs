@@ -0,0 +1,33 @@
+use std::time::Duration;
+use crate::bus::queries::inter::query::Query;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum TimerKind {
+    Smudge,
+    Obambo
+}
+
+#[derive(Debug, Clone)]
+pub struct TimerQuery {
+    pub(crate) timer: TimerKind
+}
+
+impl TimerQuery {
+
+    pub fn new(timer: TimerKind) -> Self {
+        Self {
+            timer
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TimerStatus {
+    pub running: bool,
+    pub elapsed: Duration
+}
+
+impl Query for TimerQuery {
+
+    type Response = TimerStatus;
+}
@@ -0,0 +1,77 @@
+use std::any::TypeId;
+use std::cell::Cell;
+use crate::bus::event_bus::{pause_event, remove_responder, resume_event, unregister_event, unregister_middleware};
+
+#[derive(Debug)]
+enum SubscriptionKind {
+    Event(String),
+    Query(TypeId),
+    Middleware
+}
+
+#[derive(Debug)]
+pub struct Subscription {
+    kind: SubscriptionKind,
+    id: u32,
+    active: Cell<bool>
+}
+
+impl Subscription {
+
+    pub(crate) fn event(event: &str, id: u32) -> Self {
+        Self::new(SubscriptionKind::Event(event.to_string()), id)
+    }
+
+    pub(crate) fn query(type_id: TypeId, id: u32) -> Self {
+        Self::new(SubscriptionKind::Query(type_id), id)
+    }
+
+    pub(crate) fn middleware(id: u32) -> Self {
+        Self::new(SubscriptionKind::Middleware, id)
+    }
+
+    fn new(kind: SubscriptionKind, id: u32) -> Self {
+        Self {
+            kind,
+            id,
+            active: Cell::new(true)
+        }
+    }
+
+    pub fn pause(&self) -> bool {
+        match &self.kind {
+            SubscriptionKind::Event(event) => pause_event(event, self.id),
+            _ => false
+        }
+    }
+
+    pub fn resume(&self) -> bool {
+        match &self.kind {
+            SubscriptionKind::Event(event) => resume_event(event, self.id),
+            _ => false
+        }
+    }
+
+    pub fn unsubscribe(&self) -> bool {
+        if !self.active.replace(false) {
+            return false;
+        }
+
+        match &self.kind {
+            SubscriptionKind::Event(event) => unregister_event(event, self.id),
+            SubscriptionKind::Query(type_id) => remove_responder(*type_id, self.id),
+            SubscriptionKind::Middleware => unregister_middleware(self.id)
+        }
+    }
+
+    pub fn detach(self) {
+        self.active.set(false);
+    }
+}
+
+impl Drop for Subscription {
+
+    fn drop(&mut self) {
+        self.unsubscribe();
+    }
+}
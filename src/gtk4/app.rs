@@ -1,8 +1,12 @@
-use gtk4::{gdk, gio, style_context_add_provider_for_display, Application, Builder, CssProvider, Settings, StyleContext};
+use gtk4::{gdk, style_context_add_provider_for_display, Application, CssProvider, Settings};
 use gtk4::gio::{resources_register, ApplicationFlags, Resource};
 use gtk4::glib::Bytes;
-use gtk4::prelude::{ApplicationExt, ApplicationExtManual, FileExt, GtkApplicationExt, ObjectExt, StaticType, StyleContextExt};
+use gtk4::prelude::{ApplicationExt, ApplicationExtManual, ObjectExt};
+use crate::bus::event_bus::register_event_filtered;
+use crate::bus::event_bus::EventPropagation::Continue;
+use crate::bus::events::action_event::ActionEvent;
 use crate::gtk4::windows::main_window::MainWindow;
+use crate::input::action::Action;
 
 pub struct App {
     app: Application
@@ -21,7 +25,7 @@ impl App {
     pub fn run(&self) {
         self.app.connect_activate(move |app| {
             if let Some(settings) = Settings::default() {
-                settings.set_property("gtk-application-prefer-dark-theme", &true);
+                settings.set_property("gtk-application-prefer-dark-theme", true);
             }
 
             let resource_data = include_bytes!("../../res/resources.gresources");
@@ -37,7 +41,18 @@ impl App {
 
             //provider.load_from_resource("/phastimer/rust/res/ui/theme.css");
 
-            MainWindow::new(&app);
+            MainWindow::new(app);
+
+            register_event_filtered("action_event", |event| {
+                event.as_any().downcast_ref::<ActionEvent>().is_some_and(|event| event.action == Action::Quit)
+            }, {
+                let app = app.clone();
+                move |_, _| {
+                    crate::settings::save();
+                    app.quit();
+                    Continue
+                }
+            }, false).detach();
 
             //register_app_actions(&app);
         });
@@ -1,12 +1,28 @@
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
+use std::io;
 use std::rc::Rc;
-use gdk4_win32::prelude::{DisplayExt, MonitorExt};
+use std::time::Duration;
 use glib::Propagation;
-use gtk4::{gdk, style_context_add_provider_for_display, ApplicationWindow, Builder, Button, ComboBoxText, CssProvider, Paned, ScrolledWindow, Switch, Window};
-use gtk4::prelude::{ButtonExt, NativeExt, RangeExt, ScaleExt, StyleContextExt, WidgetExt};
-use crate::gtk4::windows::main_window::win32_move_to_x_and_topmost;
-use crate::settings::{save_settings, verify_key_bind, BLOOD_MOON, GHOST_SPEED, KEY_MS, KEY_OBAMBO_RESET, KEY_OBAMBO_START, KEY_RESET, KEY_TIMER_RESET, KEY_TIMER_START, LOCATION};
-use crate::utils::keys::gtk4_key_to_key;
+use gtk4::{gdk, style_context_add_provider_for_display, ApplicationWindow, Builder, Button, CssProvider, DropDown, GestureClick, Label, Switch};
+use gtk4::prelude::{ButtonExt, Cast, GestureSingleExt, RangeExt, ScaleExt, WidgetExt};
+use crate::bus::event_bus::{query, register_event};
+use crate::bus::event_bus::EventPropagation::Continue;
+use crate::bus::events::config_problems_event::ConfigProblemsEvent;
+use crate::bus::events::save_result_event::SaveResultEvent;
+use crate::bus::events::settings_event::SettingsEvent;
+use crate::bus::events::timer_state_event::TimerStateEvent;
+use crate::bus::queries::speed_query::SpeedQuery;
+use crate::bus::queries::timer_query::{TimerKind, TimerQuery};
+use crate::gtk4::windows::main_window::place;
+use crate::input::action::Action;
+use crate::input::bindings::Binding;
+use crate::input::capture::{cancel_capture, start_capture, CaptureResult};
+use crate::input::chord::Chord;
+use crate::input::inputs::{Input, MouseButton};
+use crate::input::layout::Layout;
+use crate::input::trigger::Trigger;
+use crate::settings;
+use crate::settings::config::Problem;
 
 pub struct ConsoleView {
     pub root: gtk4::Box
@@ -14,7 +30,7 @@ pub struct ConsoleView {
 
 impl ConsoleView {
 
-    pub fn new(app_window: &ApplicationWindow, window: &Window) -> Self {
+    pub fn new(app_window: &ApplicationWindow) -> Self {
         let builder = Builder::from_resource("/phastimer/rust/res/ui/console_view.ui");
 
         let provider = CssProvider::new();
@@ -25,10 +41,87 @@ impl ConsoleView {
             .object("root")
             .expect("Couldn't find 'root' in console_view.ui");
 
+        let config_problems: Label = builder
+            .object("config_problems")
+            .expect("Couldn't find 'config_problems' in console_view.ui");
+
+        let config_problems_listener = register_event("config_problems_event", {
+            let config_problems = config_problems.clone();
+            move |_, event| {
+                let event = event.as_any().downcast_ref::<ConfigProblemsEvent>().unwrap();
+                show_config_problems(&config_problems, &event.problems, event.rejected);
+                Continue
+            }
+        }, false);
+
+        let save_error: Label = builder
+            .object("save_error")
+            .expect("Couldn't find 'save_error' in console_view.ui");
+
+        let save_result_listener = register_event("save_result_event", {
+            let save_error = save_error.clone();
+            move |_, event| {
+                let event = event.as_any().downcast_ref::<SaveResultEvent>().unwrap();
+                match &event.error {
+                    Some(error) => show_save_error(&save_error, &format!("Couldn't save settings: {}", error)),
+                    None => save_error.set_visible(false)
+                }
+                Continue
+            }
+        }, false);
+
+        let timer_status: Label = builder
+            .object("timer_status")
+            .expect("Couldn't find 'timer_status' in console_view.ui");
+        show_timer_status(&timer_status);
+
+        // The timers live in the main view, so ask it instead of keeping a copy here,
+        // and only keep asking while one of them runs
+        let status_ticker = Rc::new(RefCell::new(None::<glib::SourceId>));
+        let timer_state_listener = register_event("timer_state_event", {
+            let timer_status = timer_status.clone();
+            let status_ticker = Rc::clone(&status_ticker);
+            move |_, event| {
+                let event = event.as_any().downcast_ref::<TimerStateEvent>().unwrap();
+                show_timer_status(&timer_status);
+
+                let running = event.smudge.is_some() || event.obambo.is_some();
+                let mut ticker = status_ticker.borrow_mut();
+                match (running, ticker.is_some()) {
+                    (true, false) => {
+                        *ticker = Some(glib::timeout_add_local(Duration::from_millis(500), {
+                            let timer_status = timer_status.clone();
+                            move || {
+                                show_timer_status(&timer_status);
+                                glib::ControlFlow::Continue
+                            }
+                        }));
+                    }
+                    (false, true) => {
+                        if let Some(ticker) = ticker.take() {
+                            ticker.remove();
+                        }
+                    }
+                    _ => {}
+                }
+                Continue
+            }
+        }, false);
+
+        let speed_listener = register_event("speed_event", {
+            let timer_status = timer_status.clone();
+            move |_, _| {
+                show_timer_status(&timer_status);
+                Continue
+            }
+        }, false);
+
+        let initial = settings::current();
+
         let ghost_speed: gtk4::Scale = builder
             .object("ghost_speed")
             .expect("Couldn't find 'ghost_speed' in console_view.ui");
-        unsafe { ghost_speed.set_value(GHOST_SPEED as f64); }
+        ghost_speed.set_value(settings::ghost_speed() as f64);
 
         const SPEED_LABELS: [&str; 5] = [
             "50%",
@@ -38,23 +131,22 @@ impl ConsoleView {
             "150%"
         ];
 
-        ghost_speed.set_format_value_func(|_, value| unsafe {
-            let idx = value.round().clamp(0.0, 4.0) as usize;
-            if idx != GHOST_SPEED {
-                GHOST_SPEED = idx;
-            }
+        ghost_speed.set_format_value_func(|_, value| {
+            SPEED_LABELS[speed_index(value)].to_string()
+        });
 
-            SPEED_LABELS[idx].to_string()
+        ghost_speed.connect_value_changed(|scale| {
+            settings::set_ghost_speed(speed_index(scale.value()));
         });
 
 
         let blood_moon_swc: Switch = builder
             .object("blood_moon_swc")
             .expect("Couldn't find 'blood_moon_swc' in console_view.ui");
-        unsafe { blood_moon_swc.set_active(BLOOD_MOON); }
+        blood_moon_swc.set_active(settings::blood_moon());
 
         blood_moon_swc.connect_state_set(|_sw, state| {
-            unsafe { BLOOD_MOON = state; }
+            settings::set_blood_moon(state);
             Propagation::Proceed
         });
 
@@ -76,130 +168,160 @@ impl ConsoleView {
         let complete_reset_btn: Button = builder
             .object("complete_reset_btn")
             .expect("Couldn't find 'complete_reset_btn' in console_view.ui");
+        let lock_btn: Button = builder
+            .object("lock_btn")
+            .expect("Couldn't find 'lock_btn' in console_view.ui");
+        let console_btn: Button = builder
+            .object("console_btn")
+            .expect("Couldn't find 'console_btn' in console_view.ui");
+        let quit_btn: Button = builder
+            .object("quit_btn")
+            .expect("Couldn't find 'quit_btn' in console_view.ui");
+
+        let auto_lock_swc: Switch = builder
+            .object("auto_lock_swc")
+            .expect("Couldn't find 'auto_lock_swc' in console_view.ui");
+        auto_lock_swc.set_active(initial.auto_lock);
+
+        auto_lock_swc.connect_state_set(|_sw, state| {
+            settings::set_auto_lock(state);
+            Propagation::Proceed
+        });
 
-        unsafe {
-            timer_start_btn.set_label(&format!("{:?}", *&raw const KEY_TIMER_START));
-            timer_reset_btn.set_label(&format!("{:?}", *&raw const KEY_TIMER_RESET));
-            obambo_start_btn.set_label(&format!("{:?}", *&raw const KEY_OBAMBO_START));
-            obambo_reset_btn.set_label(&format!("{:?}", *&raw const KEY_OBAMBO_RESET));
-            ms_btn.set_label(&format!("{:?}", *&raw const KEY_MS));
-            complete_reset_btn.set_label(&format!("{:?}", *&raw const KEY_RESET));
-        }
-
-        let capture_next = Rc::new(Cell::new(false));
-        let capture_target = Rc::new(Cell::new(0));
+        let binding_buttons = Rc::new(vec![
+            (Action::SmudgeStart, timer_start_btn),
+            (Action::SmudgeReset, timer_reset_btn),
+            (Action::ObamboStart, obambo_start_btn),
+            (Action::ObamboReset, obambo_reset_btn),
+            (Action::Tap, ms_btn),
+            (Action::ResetAll, complete_reset_btn),
+            (Action::ToggleLock, lock_btn),
+            (Action::OpenConsole, console_btn),
+            (Action::Quit, quit_btn)
+        ]);
+
+        const TRIGGERS: [(&str, Trigger); 5] = [
+            ("Tap", Trigger::Tap),
+            ("Double tap", Trigger::DoubleTap),
+            ("Hold 0.5s", Trigger::Hold(Duration::from_millis(500))),
+            ("Hold 1s", Trigger::Hold(Duration::from_millis(1000))),
+            ("Hold 2s", Trigger::Hold(Duration::from_millis(2000)))
+        ];
 
-        timer_start_btn.connect_clicked({
-            let capture_next = capture_next.clone();
-            let mut capture_target = capture_target.clone();
-            move |_| {
-                capture_target.set(0);
-                capture_next.set(true);
+        // Set while widgets follow a change made elsewhere, so they don't write it back
+        let refreshing = Rc::new(Cell::new(false));
+        let mut trigger_dropdowns = Vec::new();
+
+        for (action, button) in binding_buttons.iter() {
+            let action = *action;
+            button.set_label(&binding_label(action));
+            button.set_tooltip_text(Some(if action == Action::OpenConsole {
+                "Click to rebind, right click to add an alternative binding"
+            } else {
+                "Click to rebind, right click to add an alternative binding, middle click to unbind"
+            }));
+
+            let trigger = DropDown::from_strings(&TRIGGERS.map(|(label, _)| label));
+            trigger.set_widget_name("trigger");
+            trigger.set_valign(gtk4::Align::Center);
+
+            let current = initial.bindings.trigger_for(action);
+            if let Some(idx) = TRIGGERS.iter().position(|(_, t)| *t == current) {
+                trigger.set_selected(idx as u32);
             }
-        });
 
-        timer_reset_btn.connect_clicked({
-            let capture_next = capture_next.clone();
-            let capture_target = capture_target.clone();
-            move |_| {
-                capture_target.set(1);
-                capture_next.set(true);
-            }
-        });
+            trigger.connect_selected_notify({
+                let binding_buttons = binding_buttons.clone();
+                let refreshing = refreshing.clone();
+                move |dropdown| {
+                    if refreshing.get() {
+                        return;
+                    }
 
-        obambo_start_btn.connect_clicked({
-            let capture_next = capture_next.clone();
-            let capture_target = capture_target.clone();
-            move |_| {
-                capture_target.set(2);
-                capture_next.set(true);
-            }
-        });
+                    let Some((_, trigger)) = TRIGGERS.get(dropdown.selected() as usize) else {
+                        return;
+                    };
 
-        obambo_reset_btn.connect_clicked({
-            let capture_next = capture_next.clone();
-            let capture_target = capture_target.clone();
-            move |_| {
-                capture_target.set(3);
-                capture_next.set(true);
-            }
-        });
+                    settings::update_bindings(|bindings| bindings.set_trigger(action, *trigger));
 
-        ms_btn.connect_clicked({
-            let capture_next = capture_next.clone();
-            let capture_target = capture_target.clone();
-            move |_| {
-                capture_target.set(4);
-                capture_next.set(true);
-            }
-        });
+                    refresh_binding_label(action, &binding_buttons);
+                }
+            });
 
-        complete_reset_btn.connect_clicked({
-            let capture_next = capture_next.clone();
-            let capture_target = capture_target.clone();
-            move |_| {
-                capture_target.set(5);
-                capture_next.set(true);
+            if let Some(parent) = button.parent().and_then(|parent| parent.downcast::<gtk4::Box>().ok()) {
+                trigger.insert_before(&parent, Some(button));
             }
-        });
 
-        let controller = gtk4::EventControllerKey::new();
+            trigger_dropdowns.push((action, trigger));
+        }
 
-        {
-            let capture_next = capture_next.clone();
-            let capture_target = capture_target.clone();
+        for (action, button) in binding_buttons.iter() {
+            let action = *action;
 
-            controller.connect_key_pressed(move |_, key, _keycode, _state| unsafe {
-                if !capture_next.get() {
-                    return Propagation::Proceed;
+            button.connect_clicked({
+                let binding_buttons = binding_buttons.clone();
+                move |_| {
+                    capture_binding(action, false, None, &binding_buttons);
                 }
+            });
 
-                capture_next.set(false);
-
-                let key = gtk4_key_to_key(&key);
-
-                if verify_key_bind(&key) {
-                    match capture_target.get() {
-                        0 => {
-                            KEY_TIMER_START = key;
-                            timer_start_btn.set_label(&format!("{:?}", key));
-                        }
-                        1 => {
-                            KEY_TIMER_RESET = key;
-                            timer_reset_btn.set_label(&format!("{:?}", key));
-                        }
-                        2 => {
-                            KEY_OBAMBO_START = key;
-                            obambo_start_btn.set_label(&format!("{:?}", key));
-                        }
-                        3 => {
-                            KEY_OBAMBO_RESET = key;
-                            obambo_start_btn.set_label(&format!("{:?}", key));
-                        }
-                        4 => {
-                            KEY_MS = key;
-                            ms_btn.set_label(&format!("{:?}", key));
-                        }
-                        5 => {
-                            KEY_RESET = key;
-                            complete_reset_btn.set_label(&format!("{:?}", key));
-                        }
-                        _ => {}
-                    }
-                    println!("{:?}", save_settings());
+            let gesture = GestureClick::new();
+            gesture.set_button(gdk::BUTTON_SECONDARY);
+            gesture.connect_pressed({
+                let binding_buttons = binding_buttons.clone();
+                move |gesture, _, _, _| {
+                    capture_binding(action, true, MouseButton::from_gdk(gesture.current_button()).map(Input::Mouse), &binding_buttons);
                 }
-
-                Propagation::Stop
             });
+            button.add_controller(gesture);
+
+            // The console can't be unbound, it's the only way back in here
+            if action != Action::OpenConsole {
+                let gesture = GestureClick::new();
+                gesture.set_button(gdk::BUTTON_MIDDLE);
+                gesture.connect_pressed({
+                    let binding_buttons = binding_buttons.clone();
+                    move |_, _, _, _| {
+                        cancel_capture();
+                        clear_binding(action, &binding_buttons);
+                    }
+                });
+                button.add_controller(gesture);
+            }
         }
 
+        const LAYOUTS: [Option<Layout>; 4] = [
+            None,
+            Some(Layout::Us),
+            Some(Layout::Azerty),
+            Some(Layout::Qwertz)
+        ];
+
+        let keyboard_layout: DropDown = builder
+            .object("keyboard_layout")
+            .expect("Couldn't find 'keyboard_layout' in console_view.ui");
+        keyboard_layout.set_selected(LAYOUTS.iter().position(|layout| *layout == initial.keyboard_layout).unwrap_or(0) as u32);
+
+        keyboard_layout.connect_selected_notify({
+            let binding_buttons = binding_buttons.clone();
+            let refreshing = refreshing.clone();
+            move |dropdown| {
+                if refreshing.get() {
+                    return;
+                }
 
+                settings::set_keyboard_layout(LAYOUTS.get(dropdown.selected() as usize).copied().flatten());
 
+                for (action, _) in binding_buttons.iter() {
+                    refresh_binding_label(*action, &binding_buttons);
+                }
+            }
+        });
 
         let location: gtk4::Scale = builder
             .object("location")
             .expect("Couldn't find 'location' in console_view.ui");
-        unsafe { location.set_value(LOCATION as f64); }
+        location.set_value(initial.location as f64);
 
         const LOCATIONS: [&str; 3] = [
             "Left",
@@ -207,45 +329,88 @@ impl ConsoleView {
             "Right"
         ];
 
-        #[cfg(windows)]
-        {
-            location.set_format_value_func({
-                let app_window = app_window.clone();
+        location.set_format_value_func(|_, value| {
+            LOCATIONS[location_index(value)].to_string()
+        });
 
-                move |_, value| unsafe {
-                    let idx = value.round().clamp(0.0, 2.0) as usize;
+        // Where the window actually is, which can lag behind a restored setting
+        let placed = Cell::new(initial.location);
 
-                    if idx != LOCATION {
-                        LOCATION = idx;
+        location.connect_value_changed({
+            let app_window = app_window.clone();
+            move |scale| {
+                let idx = location_index(scale.value());
 
-                        match idx {
-                            1 => {
-                                app_window.style_context().add_class("left");
-                                app_window.style_context().add_class("right");
-                                win32_move_to_x_and_topmost(&app_window, (get_screen_width(&app_window)-app_window.allocated_width()) / 2, true)
-                            }
-                            2 => {
-                                app_window.style_context().remove_class("left");
-                                app_window.style_context().add_class("right");
-                                win32_move_to_x_and_topmost(&app_window, get_screen_width(&app_window)-app_window.allocated_width(), true)
-                            }
-                            _ => {
-                                app_window.style_context().remove_class("right");
-                                app_window.style_context().add_class("left");
-                                win32_move_to_x_and_topmost(&app_window, 0, true)
-                            }
-                        }
+                if idx != placed.get() {
+                    placed.set(idx);
+                    settings::set_location(idx);
+                    place(&app_window, idx);
+                }
+            }
+        });
 
-                        println!("{:?}", save_settings());
-                    }
+        let restore_btn: Button = builder
+            .object("restore_btn")
+            .expect("Couldn't find 'restore_btn' in console_view.ui");
 
-                    LOCATIONS[idx].to_string()
+        restore_btn.connect_clicked({
+            let save_error = save_error.clone();
+            move |_| {
+                match settings::restore_previous() {
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => show_save_error(&save_error, "There are no earlier settings to restore"),
+                    Err(err) => show_save_error(&save_error, &format!("Couldn't restore settings: {}", err)),
+                    Ok(()) => {}
                 }
-            });
-        }
+            }
+        });
+
+        let reset_btn: Button = builder
+            .object("reset_btn")
+            .expect("Couldn't find 'reset_btn' in console_view.ui");
 
+        reset_btn.connect_clicked(|_| settings::reset_to_defaults());
 
-        window.add_controller(controller);
+        // Restoring, resetting or another window can change settings under the widgets
+        let settings_event_listener = register_event("settings_event", {
+            let binding_buttons = binding_buttons.clone();
+            move |_, event| {
+                let event = event.as_any().downcast_ref::<SettingsEvent>().unwrap();
+                let settings = &event.settings;
+
+                refreshing.set(true);
+
+                ghost_speed.set_value(settings.ghost_speed as f64);
+                blood_moon_swc.set_active(settings.blood_moon);
+                auto_lock_swc.set_active(settings.auto_lock);
+                location.set_value(settings.location as f64);
+                keyboard_layout.set_selected(LAYOUTS.iter().position(|layout| *layout == settings.keyboard_layout).unwrap_or(0) as u32);
+
+                for (action, trigger) in &trigger_dropdowns {
+                    let current = settings.bindings.trigger_for(*action);
+                    if let Some(idx) = TRIGGERS.iter().position(|(_, t)| *t == current) {
+                        trigger.set_selected(idx as u32);
+                    }
+
+                    refresh_binding_label(*action, &binding_buttons);
+                }
+
+                refreshing.set(false);
+                Continue
+            }
+        }, false);
+
+        root.connect_destroy(move |_| {
+            cancel_capture();
+            config_problems_listener.unsubscribe();
+            save_result_listener.unsubscribe();
+            settings_event_listener.unsubscribe();
+            timer_state_listener.unsubscribe();
+            speed_listener.unsubscribe();
+
+            if let Some(ticker) = status_ticker.borrow_mut().take() {
+                ticker.remove();
+            }
+        });
 
         Self {
             root
@@ -253,12 +418,103 @@ impl ConsoleView {
     }
 }
 
-pub fn get_screen_width(window: &ApplicationWindow) -> i32 {
-    let display = window.display();
-    let surface = window.surface().expect("Window not realized yet");
-    let monitor = display.monitor_at_surface(&surface)
-        .expect("No monitor found");
+fn speed_index(value: f64) -> usize {
+    value.round().clamp(0.0, 4.0) as usize
+}
+
+fn location_index(value: f64) -> usize {
+    value.round().clamp(0.0, 2.0) as usize
+}
+
+fn show_config_problems(label: &Label, problems: &[Problem], rejected: bool) {
+    let mut text = String::from(if rejected {
+        "The config file was edited but has problems, so none of the edit was applied:"
+    } else {
+        "Some settings in the config file couldn't be used, their defaults apply instead:"
+    });
+    for problem in problems {
+        text.push_str(&format!("\n\u{2022} {}", problem));
+    }
+
+    label.set_text(&text);
+    label.set_visible(!problems.is_empty());
+}
+
+fn show_save_error(label: &Label, text: &str) {
+    label.set_text(text);
+    label.set_visible(true);
+}
+
+fn show_timer_status(label: &Label) {
+    let timer = |kind| match query(TimerQuery::new(kind)) {
+        Some(status) if status.running => format!("{:02}:{:02}", status.elapsed.as_secs() / 60, status.elapsed.as_secs() % 60),
+        Some(_) => String::from("stopped"),
+        None => String::from("-")
+    };
+
+    let speed = match query(SpeedQuery::new()).flatten() {
+        Some(speed) => format!("{:.2} m/s", speed),
+        None => String::from("-")
+    };
+
+    label.set_text(&format!("Smudge {}   Obambo {}   Speed {}", timer(TimerKind::Smudge), timer(TimerKind::Obambo), speed));
+}
+
+fn binding_label(action: Action) -> String {
+    let bound = settings::with_bindings(|bindings| bindings.bindings_for(action));
+    if bound.is_empty() {
+        return String::from("Unbound");
+    }
+
+    bound.iter()
+        .map(|binding| binding.label())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn refresh_binding_label(action: Action, binding_buttons: &[(Action, Button)]) {
+    for (a, button) in binding_buttons {
+        if *a == action {
+            button.set_label(&binding_label(action));
+        }
+    }
+}
+
+fn capture_binding(action: Action, append: bool, started_by: Option<Input>, binding_buttons: &Rc<Vec<(Action, Button)>>) {
+    for (a, button) in binding_buttons.iter() {
+        if *a == action {
+            button.set_label("Press a key\u{2026} (Esc to cancel)");
+        }
+    }
+
+    start_capture(started_by, {
+        let binding_buttons = binding_buttons.clone();
+        move |result| {
+            match result {
+                CaptureResult::Captured(chord) => apply_binding(action, append, chord, &binding_buttons),
+                CaptureResult::Cancelled | CaptureResult::TimedOut => refresh_binding_label(action, &binding_buttons)
+            }
+        }
+    });
+}
+
+fn apply_binding(action: Action, append: bool, chord: Chord, binding_buttons: &[(Action, Button)]) {
+    if !settings::verify_key_bind(&chord, action) {
+        refresh_binding_label(action, binding_buttons);
+        return;
+    }
+
+    settings::update_bindings(|bindings| {
+        let mut bound = if append { bindings.bindings_for(action) } else { Vec::new() };
+        bound.push(Binding::new(chord, bindings.trigger_for(action)));
+        bindings.set(action, &bound);
+    });
+
+    refresh_binding_label(action, binding_buttons);
+}
+
+fn clear_binding(action: Action, binding_buttons: &[(Action, Button)]) {
+    settings::update_bindings(|bindings| bindings.unbind(action));
 
-    let geometry = monitor.geometry();
-    geometry.width()
+    refresh_binding_label(action, binding_buttons);
 }
@@ -1,33 +1,45 @@
 use std::rc::Rc;
-use gtk4::{gdk, style_context_add_provider_for_display, ApplicationWindow, Builder, CssProvider, GestureClick, GridView, Label, ListItem, MultiSelection, NoSelection, Orientation, SignalListItemFactory, SingleSelection, StringObject, Widget, Window};
-use gtk4::gio::ListStore;
-use gtk4::prelude::{BoxExt, Cast, EventControllerExt, GestureSingleExt, GtkWindowExt, ListItemExt, ListModelExt, SelectionModelExt, StaticType, WidgetExt};
+use gtk4::{gdk, style_context_add_provider_for_display, Builder, CssProvider, Label, ProgressBar, TickCallbackId, Widget};
+use gtk4::prelude::{Cast, GtkWindowExt, WidgetExt, WidgetExtManual};
 use crate::gtk4::views::inter::stackable::Stackable;
 use crate::gtk4::windows::main_window::MainWindow;
 
 use std::cell::{Cell, RefCell};
-use std::collections::{HashMap, VecDeque};
-use std::fmt::format;
-use std::process::exit;
-use std::sync::{Arc, Mutex};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::thread;
-use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
-use glib::clone::Downgrade;
+use std::time::{Duration, Instant};
 use glib::Propagation;
-use rdev::{listen, EventType, Key};
-use crate::bus::event_bus::{pause_event, register_event, resume_event, unregister_event};
+use crate::bus::event_bus::{clear_sticky_event, register_event, register_query, send_event, send_event_every_after, ScheduleHandle};
 use crate::bus::event_bus::EventPropagation::Continue;
-use crate::bus::events::button_event::ButtonEvent;
-use crate::bus::events::timer_event::TimerEvent;
+use crate::bus::events::action_event::ActionEvent;
+use crate::bus::events::hold_progress_event::HoldProgressEvent;
+use crate::bus::events::lock_event::LockEvent;
+use crate::bus::events::obambo_event::ObamboEvent;
+use crate::bus::events::settings_event::SettingsEvent;
+use crate::bus::events::speed_event::SpeedEvent;
+use crate::bus::events::timer_state_event::TimerStateEvent;
+use crate::bus::queries::speed_query::SpeedQuery;
+use crate::bus::queries::timer_query::{TimerKind, TimerQuery, TimerStatus};
+use crate::bus::subscription::Subscription;
 use crate::gtk4::windows::console_window::ConsoleWindow;
-use crate::settings::{BLOOD_MOON, GHOST_SPEED, KEY_MS, KEY_OBAMBO_RESET, KEY_OBAMBO_START, KEY_RESET, KEY_TIMER_RESET, KEY_TIMER_START};
+use crate::input::action::Action;
 use crate::utils::bpm::TapState;
+use crate::utils::clock;
+
+const OBAMBO_FIRST_FLIP: Duration = Duration::from_secs(60);
+const OBAMBO_FLIP_INTERVAL: Duration = Duration::from_secs(120);
 
 pub struct MainView {
     pub root: gtk4::Box,
-    pub button_event_listener: Option<RefCell<u32>>,
-    pub timer_event_listener: Option<RefCell<u32>>
+    pub action_event_listener: Subscription,
+    pub hold_progress_listener: Subscription,
+    pub lock_event_listener: Subscription,
+    pub obambo_event_listener: Subscription,
+    pub settings_event_listener: Subscription,
+    pub speed_event_listener: Subscription,
+    pub timer_query_responder: Subscription,
+    pub speed_query_responder: Subscription,
+    pub smudge_ticker: Rc<RefCell<Option<TickCallbackId>>>,
+    pub obambo_schedule: Rc<RefCell<Option<ScheduleHandle>>>
 }
 
 impl MainView {
@@ -48,53 +60,131 @@ impl MainView {
             .object("smudge")
             .expect("Couldn't find 'smudge' in main_view.ui");
 
-        let obombo: Label = builder
-            .object("obombo")
-            .expect("Couldn't find 'obombo' in main_view.ui");
+        let obambo: Label = builder
+            .object("obambo")
+            .expect("Couldn't find 'obambo' in main_view.ui");
 
         let bps: Label = builder
             .object("bps")
             .expect("Couldn't find 'bps' in main_view.ui");
 
+        let lock_badge: Label = builder
+            .object("lock_badge")
+            .expect("Couldn't find 'lock_badge' in main_view.ui");
+
+        let hold_progress: ProgressBar = builder
+            .object("hold_progress")
+            .expect("Couldn't find 'hold_progress' in main_view.ui");
+
 
         let smudge_timer_running = Rc::new(AtomicBool::new(false));
-        let obombo_timer_running = Rc::new(AtomicBool::new(false));
-        let smudge_now = Rc::new(RefCell::new(0u128));
-        let obombo_now = Rc::new(RefCell::new(0u128));
-        let mut obombo_state = Rc::new(RefCell::new(true));
+        let obambo_timer_running = Rc::new(AtomicBool::new(false));
+        let smudge_now = Rc::new(RefCell::new(clock::now()));
+        let smudge_ticker = Rc::new(RefCell::new(None::<TickCallbackId>));
+        let obambo_now = Rc::new(RefCell::new(clock::now()));
+        let obambo_schedule = Rc::new(RefCell::new(None::<ScheduleHandle>));
+        let obambo_state = Rc::new(RefCell::new(true));
+        let last_speed = Rc::new(Cell::new(None::<f64>));
+        let ghost_speed = Rc::new(Cell::new(2usize));
+        let blood_moon = Rc::new(Cell::new(false));
+
+        let settings_event_listener = register_event("settings_event", {
+            let ghost_speed = Rc::clone(&ghost_speed);
+            let blood_moon = Rc::clone(&blood_moon);
+
+            move |_, event| {
+                let event = event.as_any().downcast_ref::<SettingsEvent>().unwrap();
+                ghost_speed.set(event.settings.ghost_speed);
+                blood_moon.set(event.settings.blood_moon);
+                Continue
+            }
+        }, false);
+
+        // Whatever measured the speed, the label shows the last one sent on the bus
+        let speed_event_listener = register_event("speed_event", {
+            let bps = bps.clone();
+            move |_, event| {
+                let event = event.as_any().downcast_ref::<SpeedEvent>().unwrap();
+                bps.set_label(&format!("{:.2} m/s", event.speed.unwrap_or(0.0)));
+                Continue
+            }
+        }, false);
 
-        let timer_event_listener = Some(RefCell::new(register_event("timer_event", {
-            let smudge = smudge.clone();
-            let obombo = obombo.clone();
+        let timer_query_responder = register_query::<TimerQuery, _>({
             let smudge_timer_running = Rc::clone(&smudge_timer_running);
             let smudge_now = Rc::clone(&smudge_now);
-            let obombo_timer_running = Rc::clone(&obombo_timer_running);
-            let obombo_now = Rc::clone(&obombo_now);
-            let obombo_state = Rc::clone(&obombo_state);
+            let obambo_timer_running = Rc::clone(&obambo_timer_running);
+            let obambo_now = Rc::clone(&obambo_now);
+
+            move |query| {
+                let (running, started) = match query.timer {
+                    TimerKind::Smudge => (smudge_timer_running.load(Ordering::Relaxed), *smudge_now.borrow()),
+                    TimerKind::Obambo => (obambo_timer_running.load(Ordering::Relaxed), *obambo_now.borrow())
+                };
+
+                TimerStatus {
+                    running,
+                    elapsed: if running { clock::now().saturating_duration_since(started) } else { Duration::ZERO }
+                }
+            }
+        });
+
+        let speed_query_responder = register_query::<SpeedQuery, _>({
+            let last_speed = Rc::clone(&last_speed);
+            move |_| last_speed.get()
+        });
+
+        let obambo_event_listener = register_event("obambo_event", {
+            let obambo = obambo.clone();
+            let obambo_timer_running = Rc::clone(&obambo_timer_running);
+            let obambo_state = Rc::clone(&obambo_state);
+
+            move |_, _| {
+                if obambo_timer_running.load(Ordering::Relaxed) {
+                    let new_state = !*obambo_state.borrow();
+                    *obambo_state.borrow_mut() = new_state;
+                    obambo.set_label(if new_state { "AGGRO" } else { "CALM" });
+                }
+
+                Continue
+            }
+        }, false);
+
+        let lock_event_listener = register_event("lock_event", {
+            let lock_badge = lock_badge.clone();
 
-            move |id, event| {
-                let event = event.as_any().downcast_ref::<TimerEvent>().unwrap();
+            move |_, event| {
+                let event = event.as_any().downcast_ref::<LockEvent>().unwrap();
+                lock_badge.set_visible(event.locked);
+                Continue
+            }
+        }, false);
 
-                if smudge_timer_running.load(Ordering::Relaxed) && event.time >= *smudge_now.borrow() {
-                    smudge.set_label(&format!("{}", ms_to_msm(event.time - *smudge_now.borrow())));
+        let hold_progress_listener = register_event("hold_progress_event", {
+            let hold_progress = hold_progress.clone();
+            let hold_ticker = RefCell::new(None::<TickCallbackId>);
+
+            move |_, event| {
+                let event = event.as_any().downcast_ref::<HoldProgressEvent>().unwrap();
+
+                if let Some(ticker) = hold_ticker.borrow_mut().take() {
+                    ticker.remove();
                 }
 
-                if obombo_timer_running.load(Ordering::Relaxed) && event.time >= *obombo_now.borrow() {
-                    let elapsed = (event.time - *obombo_now.borrow())/10;
-                    if elapsed < 6000 {
-                        if *obombo_state.borrow() {
-                            *obombo_state.borrow_mut() = false;
-                        }
-                    } else if (elapsed - 6000) % 12000 == 0 {
-                        let new_state = !*obombo_state.borrow();
-                        *obombo_state.borrow_mut() = new_state;
-                        obombo.set_label(if new_state { "AGGRO" } else { "CALM" });
+                match event.started {
+                    Some(started) => {
+                        hold_progress.set_fraction(0.0);
+                        hold_progress.set_visible(true);
+                        *hold_ticker.borrow_mut() = Some(start_hold_ticker(&hold_progress, started, event.duration));
+                    }
+                    None => {
+                        hold_progress.set_visible(false);
                     }
                 }
 
                 Continue
             }
-        }, false)));
+        }, false);
 
         const SPEEDS: [f64; 5] = [
             0.5,
@@ -104,19 +194,29 @@ impl MainView {
             1.50
         ];
 
-        let button_event_listener = Some(RefCell::new(register_event("button_event", {
+        let action_event_listener = register_event("action_event", {
             let smudge = smudge.clone();
-            let obombo = obombo.clone();
+            let obambo = obambo.clone();
             let window = window.window.clone();
-            let console_window = Rc::new(RefCell::new(None::<ConsoleWindow>));;
+            let console_window = Rc::new(RefCell::new(None::<ConsoleWindow>));
+
+            let obambo_timer_running = Rc::clone(&obambo_timer_running);
+            let obambo_state = Rc::clone(&obambo_state);
+            let obambo_now = Rc::clone(&obambo_now);
+            let smudge_ticker = Rc::clone(&smudge_ticker);
+            let obambo_schedule = Rc::clone(&obambo_schedule);
+            let last_speed = Rc::clone(&last_speed);
+            let ghost_speed = Rc::clone(&ghost_speed);
+            let blood_moon = Rc::clone(&blood_moon);
 
             let tap_state = RefCell::new(TapState::default());
 
-            move |id, event| unsafe {
-                let event = event.as_any().downcast_ref::<ButtonEvent>().unwrap();
+            move |_, event| {
+                let event = event.as_any().downcast_ref::<ActionEvent>().unwrap();
+                let timers_before = timer_state(&smudge_timer_running, &smudge_now, &obambo_timer_running, &obambo_now);
 
-                match event.button {
-                    Key::ControlRight => {
+                match event.action {
+                    Action::OpenConsole => {
                         if let Some(weak) = console_window.borrow().as_ref() {
                             weak.window.present();
                             weak.window.grab_focus();
@@ -134,66 +234,105 @@ impl MainView {
 
                         *console_window.borrow_mut() = Some(console_wndw);
                     }
-                    k if k == KEY_TIMER_START => {
-                        *smudge_now.borrow_mut() = SystemTime::now()
-                            .duration_since(UNIX_EPOCH)
-                            .unwrap()
-                            .as_millis();
+                    Action::SmudgeStart => {
+                        *smudge_now.borrow_mut() = event.time;
                         smudge_timer_running.store(true, Ordering::Relaxed);
+
+                        if smudge_ticker.borrow().is_none() {
+                            *smudge_ticker.borrow_mut() = Some(start_smudge_ticker(&smudge, &smudge_now));
+                        }
                     }
-                    k if k == KEY_TIMER_RESET => {
+                    Action::SmudgeReset => {
+                        if let Some(ticker) = smudge_ticker.borrow_mut().take() {
+                            ticker.remove();
+                        }
+
                         smudge_timer_running.store(false, Ordering::Relaxed);
                         smudge.set_label("00:00.00");
                     }
-                    k if k == KEY_OBAMBO_START => {
-                        *obombo_now.borrow_mut() = SystemTime::now()
-                            .duration_since(UNIX_EPOCH)
-                            .unwrap()
-                            .as_millis();
-                        obombo_timer_running.store(true, Ordering::Relaxed);
-                        obombo.set_label("CALM");
+                    Action::ObamboStart => {
+                        if let Some(handle) = obambo_schedule.borrow_mut().take() {
+                            handle.cancel();
+                        }
+
+                        // Counted from the press, not from when the main loop got round to it
+                        let first_flip = (event.time + OBAMBO_FIRST_FLIP).saturating_duration_since(clock::now());
+                        *obambo_schedule.borrow_mut() = Some(send_event_every_after(first_flip, OBAMBO_FLIP_INTERVAL, || {
+                            Box::new(ObamboEvent::new())
+                        }));
+                        *obambo_now.borrow_mut() = event.time;
+                        obambo_timer_running.store(true, Ordering::Relaxed);
+                        *obambo_state.borrow_mut() = false;
+                        obambo.set_label("CALM");
                     }
-                    k if k == KEY_OBAMBO_RESET => {
-                        obombo_timer_running.store(false, Ordering::Relaxed);
-                        *obombo_state.borrow_mut() = true;
-                        obombo.set_label("NONE");
+                    Action::ObamboReset => {
+                        if let Some(handle) = obambo_schedule.borrow_mut().take() {
+                            handle.cancel();
+                        }
+
+                        obambo_timer_running.store(false, Ordering::Relaxed);
+                        *obambo_state.borrow_mut() = true;
+                        obambo.set_label("NONE");
                     }
-                    k if k == KEY_RESET => {
+                    Action::ResetAll => {
+                        if let Some(ticker) = smudge_ticker.borrow_mut().take() {
+                            ticker.remove();
+                        }
+
                         smudge_timer_running.store(false, Ordering::Relaxed);
                         smudge.set_label("00:00.00");
 
-                        obombo_timer_running.store(false, Ordering::Relaxed);
-                        *obombo_state.borrow_mut() = true;
-                        obombo.set_label("NONE");
+                        if let Some(handle) = obambo_schedule.borrow_mut().take() {
+                            handle.cancel();
+                        }
+
+                        obambo_timer_running.store(false, Ordering::Relaxed);
+                        *obambo_state.borrow_mut() = true;
+                        obambo.set_label("NONE");
 
                         tap_state.borrow_mut().reset();
-                        bps.set_label("0.00 m/s");
+                        last_speed.set(None);
+                        send_event(Box::new(SpeedEvent::new(None)));
                     }
-                    k if k == KEY_MS => {
-                        if let Some((bpm, mut ms)) = tap_state.borrow_mut().tap_and_compute() {
-                            ms = ms/SPEEDS[GHOST_SPEED];
+                    Action::Tap => {
+                        if let Some((_, mut ms)) = tap_state.borrow_mut().tap_at_and_compute(event.time) {
+                            ms /= SPEEDS[ghost_speed.get()];
 
-                            if BLOOD_MOON {
-                                ms = ms*0.85;
+                            if blood_moon.get() {
+                                ms *= 0.85;
                             }
 
-                            bps.set_label(&format!("{:.2} m/s", ms));
-
+                            last_speed.set(Some(ms));
+                            send_event(Box::new(SpeedEvent::new(Some(ms))));
                         } else {
-                            bps.set_label("0.00 m/s");
+                            last_speed.set(None);
+                            send_event(Box::new(SpeedEvent::new(None)));
                         }
                     }
                     _ => {}
                 }
 
+                let timers = timer_state(&smudge_timer_running, &smudge_now, &obambo_timer_running, &obambo_now);
+                if timers != timers_before {
+                    send_event(Box::new(TimerStateEvent::new(timers.0, timers.1)));
+                }
+
                 Continue
             }
-        }, false)));
+        }, false);
 
         Self {
             root,
-            button_event_listener,
-            timer_event_listener
+            action_event_listener,
+            hold_progress_listener,
+            lock_event_listener,
+            obambo_event_listener,
+            settings_event_listener,
+            speed_event_listener,
+            timer_query_responder,
+            speed_query_responder,
+            smudge_ticker,
+            obambo_schedule
         }
     }
 }
@@ -214,29 +353,78 @@ impl Stackable for MainView {
 
     fn on_resume(&self) {
         //(self.show_title_bar)(true);
+        self.action_event_listener.resume();
+        self.hold_progress_listener.resume();
+        self.lock_event_listener.resume();
+        self.obambo_event_listener.resume();
+        self.settings_event_listener.resume();
+        self.speed_event_listener.resume();
     }
 
     fn on_pause(&self) {
-        if let Some(button_event_listener) = &self.button_event_listener {
-            pause_event("button_event", *button_event_listener.borrow());
-        }
-
-        if let Some(timer_event_listener) = &self.timer_event_listener {
-            pause_event("timer_event", *timer_event_listener.borrow());
-        }
+        self.action_event_listener.pause();
+        self.hold_progress_listener.pause();
+        self.lock_event_listener.pause();
+        self.obambo_event_listener.pause();
+        self.settings_event_listener.pause();
+        self.speed_event_listener.pause();
     }
 
     fn on_destroy(&self) {
-        if let Some(button_event_listener) = &self.button_event_listener {
-            unregister_event("button_event", *button_event_listener.borrow());
+        self.action_event_listener.unsubscribe();
+        self.hold_progress_listener.unsubscribe();
+        self.lock_event_listener.unsubscribe();
+        self.obambo_event_listener.unsubscribe();
+        self.settings_event_listener.unsubscribe();
+        self.speed_event_listener.unsubscribe();
+        self.timer_query_responder.unsubscribe();
+        self.speed_query_responder.unsubscribe();
+
+        // The timers die with the view, a view opened later shouldn't pick up their last state
+        clear_sticky_event("timer_state_event");
+        clear_sticky_event("speed_event");
+
+        if let Some(ticker) = self.smudge_ticker.borrow_mut().take() {
+            ticker.remove();
         }
 
-        if let Some(timer_event_listener) = &self.timer_event_listener {
-            unregister_event("timer_event", *timer_event_listener.borrow());
+        if let Some(handle) = self.obambo_schedule.borrow_mut().take() {
+            handle.cancel();
         }
     }
 }
 
+fn timer_state(smudge_running: &AtomicBool, smudge_now: &RefCell<Instant>, obambo_running: &AtomicBool, obambo_now: &RefCell<Instant>) -> (Option<Instant>, Option<Instant>) {
+    (
+        if smudge_running.load(Ordering::Relaxed) { Some(*smudge_now.borrow()) } else { None },
+        if obambo_running.load(Ordering::Relaxed) { Some(*obambo_now.borrow()) } else { None }
+    )
+}
+
+fn start_smudge_ticker(smudge: &Label, smudge_now: &Rc<RefCell<Instant>>) -> TickCallbackId {
+    let smudge_now = Rc::clone(smudge_now);
+
+    smudge.add_tick_callback(move |smudge, _| {
+        let elapsed = clock::now().saturating_duration_since(*smudge_now.borrow());
+        let text = ms_to_msm(elapsed.as_millis());
+
+        if smudge.label().as_str() != text {
+            smudge.set_label(&text);
+        }
+
+        glib::ControlFlow::Continue
+    })
+}
+
+fn start_hold_ticker(progress: &ProgressBar, started: Instant, duration: Duration) -> TickCallbackId {
+    progress.add_tick_callback(move |progress, _| {
+        let elapsed = clock::now().saturating_duration_since(started);
+        progress.set_fraction((elapsed.as_secs_f64() / duration.as_secs_f64().max(f64::EPSILON)).min(1.0));
+
+        glib::ControlFlow::Continue
+    })
+}
+
 fn ms_to_msm(ms: u128) -> String {
     let mut total_seconds = ms / 1000;
     let mut centiseconds = ((ms % 1000) + 5) / 10; // rounded
@@ -1,4 +1,4 @@
-use gtk4::{style_context_add_provider_for_display, Application, ApplicationWindow, Builder, CssProvider, Stack, StackPage, Window};
+use gtk4::{ApplicationWindow, Window};
 use gtk4::prelude::{GtkWindowExt, WidgetExt};
 use crate::gtk4::views::console_view::ConsoleView;
 
@@ -14,7 +14,7 @@ impl ConsoleWindow {
         window.set_title(Some("PhasTimer"));
         window.set_default_size(1200, 700);
 
-        let view = ConsoleView::new(app_window, &window);
+        let view = ConsoleView::new(app_window);
 
         window.set_child(Some(&view.root));
         window.show();
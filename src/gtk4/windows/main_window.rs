@@ -1,15 +1,14 @@
 use std::cell::RefCell;
 use std::collections::HashMap;
-use std::process::exit;
 use std::rc::Rc;
-use gdk4_win32::glib::translate::ToGlibPtr;
 use glib::object::ObjectExt;
 use gtk4::{gdk, style_context_add_provider_for_display, Application, ApplicationWindow, Builder, CssProvider, Stack, StackPage};
-use gtk4::prelude::{Cast, GtkWindowExt, ListModelExt, NativeExt, StyleContextExt, WidgetExt};
-use crate::gtk4::views::console_view::get_screen_width;
+use gtk4::prelude::{ApplicationExt, Cast, GtkWindowExt, ListModelExt, StyleContextExt, WidgetExt};
+use crate::bus::event_bus::get_sticky_event;
+use crate::bus::events::settings_event::SettingsEvent;
 use crate::gtk4::views::inter::stackable::Stackable;
 use crate::gtk4::views::main_view::MainView;
-use crate::settings::LOCATION;
+use crate::settings::location;
 
 #[derive(Clone)]
 pub struct MainWindow {
@@ -33,7 +32,11 @@ impl MainWindow {
             .expect("Failed to get the 'main_window' from window.ui");
 
         window.set_application(Some(app));
-        window.connect_destroy(|_| exit(0));
+        window.connect_destroy(|window| {
+            if let Some(app) = window.application() {
+                app.quit();
+            }
+        });
         window.set_decorated(false);
 
 
@@ -49,7 +52,7 @@ impl MainWindow {
 
         stack.connect_visible_child_name_notify({
             let views = views.clone();
-            let mut previous = RefCell::new(String::new());
+            let previous = RefCell::new(String::new());
             move |stack| {
                 let current = stack.visible_child_name().unwrap_or_default().to_string();
 
@@ -68,46 +71,7 @@ impl MainWindow {
             }
         });
 
-        #[cfg(windows)]
-        {
-            window.connect_map(|window| {
-                let win_weak = window.downgrade();
-
-                glib::idle_add_local(move || {
-                    let Some(window) = win_weak.upgrade() else {
-                        return glib::ControlFlow::Break;
-                    };
-
-                    let w = window.width();
-                    let h = window.height();
-
-                    if w > 0 && h > 0 {
-                        match unsafe { LOCATION } {
-                            1 => {
-                                window.style_context().add_class("left");
-                                window.style_context().add_class("right");
-                                win32_move_to_x_and_topmost(&window, (get_screen_width(&window)-window.allocated_width()) / 2, true)
-                            }
-                            2 => {
-                                window.style_context().remove_class("left");
-                                window.style_context().add_class("right");
-                                win32_move_to_x_and_topmost(&window, get_screen_width(&window)-window.allocated_width(), true)
-                            }
-                            _ => {
-                                window.style_context().remove_class("right");
-                                window.style_context().add_class("left");
-                                win32_move_to_x_and_topmost(&window, 0, true)
-                            }
-                        }
-
-                        glib::ControlFlow::Break
-
-                    } else {
-                        glib::ControlFlow::Continue
-                    }
-                });
-            });
-        }
+        window.connect_map(place_on_map);
 
         let _self = Self {
             window,
@@ -184,9 +148,81 @@ impl MainWindow {
     }
 }
 
+// Waits for the first size allocation, the placement needs the window's width
+fn place_on_map(window: &ApplicationWindow) {
+    let win_weak = window.downgrade();
+
+    glib::idle_add_local(move || {
+        let Some(window) = win_weak.upgrade() else {
+            return glib::ControlFlow::Break;
+        };
+
+        let w = window.width();
+        let h = window.height();
+
+        if w > 0 && h > 0 {
+            let location = get_sticky_event("settings_event")
+                .and_then(|event| event.as_any().downcast_ref::<SettingsEvent>().map(|event| event.settings.location))
+                .unwrap_or_else(location);
+
+            place(&window, location);
+
+            glib::ControlFlow::Break
+
+        } else {
+            glib::ControlFlow::Continue
+        }
+    });
+}
+
+// 0 is left, 1 the top middle and 2 right, as the console's location scale counts them
+pub fn place(window: &ApplicationWindow, location: usize) {
+    let (left, right) = match location {
+        1 => (true, true),
+        2 => (false, true),
+        _ => (true, false)
+    };
+
+    for (class, on) in [("left", left), ("right", right)] {
+        if on {
+            window.style_context().add_class(class);
+        } else {
+            window.style_context().remove_class(class);
+        }
+    }
+
+    // Only Win32 lets a window move itself, elsewhere the compositor decides
+    #[cfg(windows)]
+    {
+        let free = get_screen_width(window) - window.allocated_width();
+        let x = match location {
+            1 => free / 2,
+            2 => free,
+            _ => 0
+        };
+        win32_move_to_x_and_topmost(window, x, true);
+    }
+}
+
+#[cfg(windows)]
+fn get_screen_width(window: &ApplicationWindow) -> i32 {
+    use gtk4::gdk::prelude::{DisplayExt, MonitorExt};
+    use gtk4::prelude::NativeExt;
+
+    let display = window.display();
+    let surface = window.surface().expect("Window not realized yet");
+    let monitor = display.monitor_at_surface(&surface)
+        .expect("No monitor found");
+
+    let geometry = monitor.geometry();
+    geometry.width()
+}
+
 #[cfg(windows)]
 fn force_always_on_top_win32(window: &gtk4::Window) {
     use glib::prelude::Cast; // for downcast
+    use glib::translate::ToGlibPtr;
+    use gtk4::prelude::NativeExt;
     use gdk4_win32::Win32Surface;
 
     // Make sure the surface exists
@@ -226,7 +262,7 @@ fn force_always_on_top_win32(window: &gtk4::Window) {
 
     unsafe {
         SetWindowPos(
-            hwnd as *mut core::ffi::c_void,
+            hwnd,
             HWND_TOPMOST,
             0, 0, 0, 0,
             SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE,
@@ -235,8 +271,10 @@ fn force_always_on_top_win32(window: &gtk4::Window) {
 }
 
 #[cfg(windows)]
-pub fn win32_move_to_x_and_topmost(window: &ApplicationWindow, x: i32, topmost: bool) {
+fn win32_move_to_x_and_topmost(window: &ApplicationWindow, x: i32, topmost: bool) {
     use gtk4::glib::prelude::Cast;
+    use glib::translate::ToGlibPtr;
+    use gtk4::prelude::NativeExt;
     use gdk4_win32::Win32Surface;
 
     // Ensure the underlying GdkSurface exists
@@ -276,7 +314,7 @@ pub fn win32_move_to_x_and_topmost(window: &ApplicationWindow, x: i32, topmost:
 
     unsafe {
         SetWindowPos(
-            hwnd as *mut core::ffi::c_void,
+            hwnd,
             insert_after,
             x, 0,            // ✅ move to top-left
             0, 0,
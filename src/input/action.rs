@@ -0,0 +1,45 @@
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Action {
+    SmudgeStart,
+    SmudgeReset,
+    ObamboStart,
+    ObamboReset,
+    Tap,
+    ResetAll,
+    ToggleLock,
+    OpenConsole,
+    Quit
+}
+
+impl Action {
+
+    pub const ALL: [Action; 9] = [
+        Action::SmudgeStart,
+        Action::SmudgeReset,
+        Action::ObamboStart,
+        Action::ObamboReset,
+        Action::Tap,
+        Action::ResetAll,
+        Action::ToggleLock,
+        Action::OpenConsole,
+        Action::Quit
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::SmudgeStart => "smudge_start",
+            Action::SmudgeReset => "smudge_reset",
+            Action::ObamboStart => "obambo_start",
+            Action::ObamboReset => "obambo_reset",
+            Action::Tap => "tap",
+            Action::ResetAll => "reset_all",
+            Action::ToggleLock => "toggle_lock",
+            Action::OpenConsole => "open_console",
+            Action::Quit => "quit"
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
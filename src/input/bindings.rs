@@ -0,0 +1,145 @@
+use std::fmt;
+use rdev::Key;
+use crate::input::action::Action;
+use crate::input::chord::{Chord, Modifiers};
+use crate::input::inputs::Input;
+use crate::input::trigger::Trigger;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Binding {
+    pub chord: Chord,
+    pub trigger: Trigger
+}
+
+impl Binding {
+
+    pub const fn new(chord: Chord, trigger: Trigger) -> Self {
+        Self {
+            chord,
+            trigger
+        }
+    }
+
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.split_once('@') {
+            Some((chord, trigger)) => Some(Self::new(Chord::parse(chord)?, Trigger::parse(trigger)?)),
+            None => Some(Self::new(Chord::parse(text)?, Trigger::Tap))
+        }
+    }
+}
+
+impl Binding {
+
+    pub fn label(&self) -> String {
+        match self.trigger {
+            Trigger::Tap => self.chord.label(),
+            Trigger::DoubleTap => format!("{} (double)", self.chord.label()),
+            Trigger::Hold(duration) => format!("{} (hold {:.1}s)", self.chord.label(), duration.as_secs_f64())
+        }
+    }
+}
+
+impl fmt::Display for Binding {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.trigger.suffix() {
+            Some(suffix) => write!(f, "{}@{}", self.chord, suffix),
+            None => write!(f, "{}", self.chord)
+        }
+    }
+}
+
+pub fn parse_bindings(text: &str) -> Option<Vec<Binding>> {
+    text.split(',')
+        .map(|part| part.trim())
+        .filter(|part| !part.is_empty())
+        .map(Binding::parse)
+        .collect()
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Bindings {
+    entries: Vec<(Binding, Action)>
+}
+
+impl Bindings {
+
+    pub const fn new() -> Self {
+        Self {
+            entries: Vec::new()
+        }
+    }
+
+    pub fn defaults() -> Self {
+        let mut bindings = Self::new();
+        bindings.bind(Binding::new(Chord::key(Key::ControlRight), Trigger::Tap), Action::OpenConsole);
+        bindings.bind(Binding::new(Chord::key(Key::BackSlash), Trigger::Tap), Action::Quit);
+        bindings.bind(Binding::new(Chord::key(Key::Num1), Trigger::Tap), Action::SmudgeStart);
+        bindings.bind(Binding::new(Chord::key(Key::Num2), Trigger::Tap), Action::SmudgeReset);
+        bindings.bind(Binding::new(Chord::key(Key::Num3), Trigger::Tap), Action::ObamboStart);
+        bindings.bind(Binding::new(Chord::key(Key::Num4), Trigger::Tap), Action::ObamboReset);
+        bindings.bind(Binding::new(Chord::key(Key::Num5), Trigger::Tap), Action::Tap);
+        bindings.bind(Binding::new(Chord::key(Key::Num0), Trigger::Tap), Action::ResetAll);
+        bindings.bind(Binding::new(Chord::key(Key::ScrollLock), Trigger::Tap), Action::ToggleLock);
+        bindings
+    }
+
+    pub fn bind(&mut self, binding: Binding, action: Action) {
+        if !self.entries.contains(&(binding, action)) {
+            self.entries.push((binding, action));
+        }
+    }
+
+    pub fn unbind(&mut self, action: Action) {
+        self.entries.retain(|(_, a)| *a != action);
+    }
+
+    pub fn set(&mut self, action: Action, bindings: &[Binding]) {
+        self.unbind(action);
+        for binding in bindings {
+            self.bind(*binding, action);
+        }
+    }
+
+    pub fn set_trigger(&mut self, action: Action, trigger: Trigger) {
+        for (binding, a) in self.entries.iter_mut() {
+            if *a == action {
+                binding.trigger = trigger;
+            }
+        }
+    }
+
+    pub fn binding_for(&self, modifiers: Modifiers, input: Input) -> Option<(Binding, Action)> {
+        self.entries.iter()
+            .find(|(binding, _)| binding.chord.matches(modifiers, input))
+            .copied()
+    }
+
+    pub fn bindings_for(&self, action: Action) -> Vec<Binding> {
+        self.entries.iter()
+            .filter(|(_, a)| *a == action)
+            .map(|(binding, _)| *binding)
+            .collect()
+    }
+
+    pub fn trigger_for(&self, action: Action) -> Trigger {
+        self.entries.iter()
+            .find(|(_, a)| *a == action)
+            .map(|(binding, _)| binding.trigger)
+            .unwrap_or_default()
+    }
+
+    pub fn conflict(&self, chord: &Chord) -> Option<Action> {
+        self.entries.iter()
+            .find(|(binding, _)| binding.chord == *chord)
+            .map(|(_, action)| *action)
+    }
+}
+
+// Entry order carries no meaning and bind() keeps entries unique
+impl PartialEq for Bindings {
+
+    fn eq(&self, other: &Self) -> bool {
+        self.entries.len() == other.entries.len() && self.entries.iter().all(|entry| other.entries.contains(entry))
+    }
+}
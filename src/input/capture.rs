@@ -0,0 +1,178 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+use std::time::Duration;
+use gtk4::glib;
+use rdev::Key;
+use crate::bus::event_bus::register_middleware;
+use crate::bus::event_bus::EventPropagation::{Continue, Stop};
+use crate::bus::events::button_event::ButtonEvent;
+use crate::bus::subscription::Subscription;
+use crate::input::chord::{Chord, Modifiers};
+use crate::input::inputs::{Input, MouseButton};
+
+pub const CAPTURE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum CaptureResult {
+    Captured(Chord),
+    Cancelled,
+    TimedOut
+}
+
+enum Step {
+    PassThrough,
+    Swallow,
+    Done(CaptureResult)
+}
+
+struct Capture {
+    _middleware: Subscription,
+    timeout: glib::SourceId,
+    on_done: Rc<dyn Fn(CaptureResult)>,
+    // Only inputs pressed after the capture started count, not the click that started it
+    pressed: HashSet<Input>,
+    // That click can still reach us through the listener after the capture started, so it's left alone until it comes up
+    started_by: Option<Input>,
+    candidate: Option<Chord>
+}
+
+thread_local! {
+    static CAPTURE: RefCell<Option<Capture>> = const { RefCell::new(None) };
+}
+
+pub fn start_capture<F>(started_by: Option<Input>, on_done: F)
+where
+    F: Fn(CaptureResult) + 'static,
+{
+    cancel_capture();
+
+    let middleware = register_middleware(|event| {
+        let Some(event) = event.as_any().downcast_ref::<ButtonEvent>() else {
+            return Continue;
+        };
+
+        match on_button(event) {
+            Step::PassThrough => Continue,
+            Step::Swallow => Stop,
+            Step::Done(result) => {
+                finish(result);
+                Stop
+            }
+        }
+    });
+
+    let timeout = glib::timeout_add_local_once(CAPTURE_TIMEOUT, || {
+        // The source is already gone once it has fired
+        if let Some(capture) = CAPTURE.with(|capture| capture.borrow_mut().take()) {
+            (capture.on_done)(CaptureResult::TimedOut);
+        }
+    });
+
+    CAPTURE.with(|capture| {
+        *capture.borrow_mut() = Some(Capture {
+            _middleware: middleware,
+            timeout,
+            on_done: Rc::new(on_done),
+            pressed: HashSet::new(),
+            started_by,
+            candidate: None
+        });
+    });
+}
+
+pub fn cancel_capture() {
+    finish(CaptureResult::Cancelled);
+}
+
+fn finish(result: CaptureResult) {
+    let Some(capture) = CAPTURE.with(|capture| capture.borrow_mut().take()) else {
+        return;
+    };
+
+    capture.timeout.remove();
+    (capture.on_done)(result);
+}
+
+fn on_button(event: &ButtonEvent) -> Step {
+    CAPTURE.with(|capture| {
+        let mut capture = capture.borrow_mut();
+        let Some(capture) = capture.as_mut() else {
+            return Step::PassThrough;
+        };
+
+        if capture.started_by == Some(event.button) {
+            if !event.is_pressed() {
+                capture.started_by = None;
+            }
+            return Step::PassThrough;
+        }
+
+        if event.is_pressed() {
+            capture.pressed.insert(event.button);
+
+            let is_modifier = event.button.as_key().and_then(Modifiers::from_key).is_some();
+            let is_plain_click = event.button == Input::Mouse(MouseButton::Left) && event.modifiers.is_empty();
+
+            if !is_modifier && !is_plain_click && capture.candidate.is_none() {
+                capture.candidate = Some(Chord::new(event.modifiers, event.button));
+            }
+
+            return Step::Swallow;
+        }
+
+        // Releases of inputs held before the capture started still belong to the router
+        if !capture.pressed.remove(&event.button) {
+            return Step::PassThrough;
+        }
+
+        if event.button == Input::Key(Key::Escape) && event.modifiers.is_empty() {
+            return Step::Done(CaptureResult::Cancelled);
+        }
+
+        match capture.candidate {
+            Some(chord) if chord.input == event.button => Step::Done(CaptureResult::Captured(chord)),
+            // A modifier let go on its own binds the modifier itself
+            None if event.button.as_key().and_then(Modifiers::from_key).is_some() => {
+                Step::Done(CaptureResult::Captured(Chord::new(event.modifiers, event.button)))
+            }
+            _ => Step::Swallow
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use super::*;
+    use crate::bus::event_bus::send_event;
+    use crate::bus::event_bus::tests::test_lock;
+    use crate::bus::events::button_event::{ButtonState, InputSource};
+    use crate::utils::clock;
+
+    fn send(button: Input, state: ButtonState) {
+        send_event(Box::new(ButtonEvent::new(button, Modifiers::NONE, state, InputSource::Mouse, clock::now())));
+    }
+
+    #[test]
+    fn the_click_that_started_the_capture_is_not_bound() {
+        let _serial = test_lock();
+
+        let result = Rc::new(Cell::new(None));
+        let right = Input::Mouse(MouseButton::Right);
+        start_capture(Some(right), {
+            let result = Rc::clone(&result);
+            move |captured| result.set(Some(captured))
+        });
+
+        // The listener's copy of the right click arrives after the capture started
+        send(right, ButtonState::Pressed);
+        send(right, ButtonState::Released);
+        assert_eq!(result.get(), None);
+
+        let middle = Input::Mouse(MouseButton::Middle);
+        send(middle, ButtonState::Pressed);
+        send(middle, ButtonState::Released);
+        assert_eq!(result.get(), Some(CaptureResult::Captured(Chord::new(Modifiers::NONE, middle))));
+    }
+}
@@ -0,0 +1,115 @@
+use std::fmt;
+use rdev::Key;
+use crate::input::inputs::Input;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
+pub struct Modifiers {
+    bits: u8
+}
+
+impl Modifiers {
+
+    pub const NONE: Modifiers = Modifiers { bits: 0 };
+    pub const CTRL: Modifiers = Modifiers { bits: 1 };
+    pub const SHIFT: Modifiers = Modifiers { bits: 1 << 1 };
+    pub const ALT: Modifiers = Modifiers { bits: 1 << 2 };
+    pub const META: Modifiers = Modifiers { bits: 1 << 3 };
+
+    const NAMES: [(Modifiers, &'static str); 4] = [
+        (Modifiers::CTRL, "Ctrl"),
+        (Modifiers::SHIFT, "Shift"),
+        (Modifiers::ALT, "Alt"),
+        (Modifiers::META, "Meta")
+    ];
+
+    pub fn from_key(key: Key) -> Option<Self> {
+        match key {
+            Key::ControlLeft | Key::ControlRight => Some(Modifiers::CTRL),
+            Key::ShiftLeft | Key::ShiftRight => Some(Modifiers::SHIFT),
+            Key::Alt | Key::AltGr => Some(Modifiers::ALT),
+            Key::MetaLeft | Key::MetaRight => Some(Modifiers::META),
+            _ => None
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::NAMES.iter()
+            .find(|(_, n)| n.eq_ignore_ascii_case(name))
+            .map(|(modifier, _)| *modifier)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits == 0
+    }
+
+    pub fn contains(&self, other: Modifiers) -> bool {
+        self.bits & other.bits == other.bits
+    }
+
+    pub fn insert(&mut self, other: Modifiers) {
+        self.bits |= other.bits;
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Chord {
+    pub modifiers: Modifiers,
+    pub input: Input
+}
+
+impl Chord {
+
+    pub const fn new(modifiers: Modifiers, input: Input) -> Self {
+        Self {
+            modifiers,
+            input
+        }
+    }
+
+    pub const fn key(key: Key) -> Self {
+        Self::new(Modifiers::NONE, Input::Key(key))
+    }
+
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut parts: Vec<&str> = text.split('+').map(|part| part.trim()).collect();
+        let input = Input::parse(parts.pop()?)?;
+
+        let mut modifiers = Modifiers::NONE;
+        for part in parts {
+            modifiers.insert(Modifiers::from_name(part)?);
+        }
+
+        Some(Self::new(modifiers, input))
+    }
+
+    pub fn matches(&self, modifiers: Modifiers, input: Input) -> bool {
+        // Exact, so Ctrl+Shift+1 doesn't also fire what Ctrl+1 is bound to
+        self.input == input && modifiers == self.modifiers
+    }
+
+    pub fn label(&self) -> String {
+        let mut label = String::new();
+        for (modifier, name) in Modifiers::NAMES {
+            if self.modifiers.contains(modifier) {
+                label.push_str(name);
+                label.push('+');
+            }
+        }
+
+        label.push_str(&self.input.label());
+        label
+    }
+}
+
+impl fmt::Display for Chord {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in Modifiers::NAMES {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{}+", name)?;
+            }
+        }
+
+        write!(f, "{}", self.input)
+    }
+}
@@ -0,0 +1,351 @@
+use std::ffi::c_ulong;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::ops::RangeInclusive;
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::Instant;
+use rdev::{Button, EventType, Key};
+use crate::utils::clock;
+
+// struct input_event: a timeval of two longs, then type (2), code (2) and value (4),
+// so 24 bytes on 64-bit targets and 16 on 32-bit ones
+const TIMEVAL_SIZE: usize = 2 * size_of::<c_ulong>();
+pub const INPUT_EVENT_SIZE: usize = TIMEVAL_SIZE + 8;
+
+const EV_KEY: u16 = 0x01;
+const EV_REL: u16 = 0x02;
+
+const REL_HWHEEL: u16 = 0x06;
+const REL_WHEEL: u16 = 0x08;
+
+const BTN_LEFT: u16 = 0x110;
+const BTN_RIGHT: u16 = 0x111;
+const BTN_MIDDLE: u16 = 0x112;
+const BTN_TASK: u16 = 0x117;
+
+// Joystick, gamepad, tablet and touchpad buttons share EV_KEY with the keyboard but aren't keys
+const NON_KEY_BUTTONS: [RangeInclusive<u16>; 3] = [
+    0x100..=0x15f, // BTN_MISC up to BTN_GEAR_UP, minus the mouse buttons handled first
+    0x220..=0x223, // BTN_DPAD_*
+    0x2c0..=0x2e7  // BTN_TRIGGER_HAPPY*
+];
+
+// X keycodes, which is what rdev reports on Linux, are evdev codes offset by 8
+const X_KEYCODE_OFFSET: u32 = 8;
+
+const KEYS: [(u16, Key); 104] = [
+    (1, Key::Escape),
+    (2, Key::Num1),
+    (3, Key::Num2),
+    (4, Key::Num3),
+    (5, Key::Num4),
+    (6, Key::Num5),
+    (7, Key::Num6),
+    (8, Key::Num7),
+    (9, Key::Num8),
+    (10, Key::Num9),
+    (11, Key::Num0),
+    (12, Key::Minus),
+    (13, Key::Equal),
+    (14, Key::Backspace),
+    (15, Key::Tab),
+    (16, Key::KeyQ),
+    (17, Key::KeyW),
+    (18, Key::KeyE),
+    (19, Key::KeyR),
+    (20, Key::KeyT),
+    (21, Key::KeyY),
+    (22, Key::KeyU),
+    (23, Key::KeyI),
+    (24, Key::KeyO),
+    (25, Key::KeyP),
+    (26, Key::LeftBracket),
+    (27, Key::RightBracket),
+    (28, Key::Return),
+    (29, Key::ControlLeft),
+    (30, Key::KeyA),
+    (31, Key::KeyS),
+    (32, Key::KeyD),
+    (33, Key::KeyF),
+    (34, Key::KeyG),
+    (35, Key::KeyH),
+    (36, Key::KeyJ),
+    (37, Key::KeyK),
+    (38, Key::KeyL),
+    (39, Key::SemiColon),
+    (40, Key::Quote),
+    (41, Key::BackQuote),
+    (42, Key::ShiftLeft),
+    (43, Key::BackSlash),
+    (44, Key::KeyZ),
+    (45, Key::KeyX),
+    (46, Key::KeyC),
+    (47, Key::KeyV),
+    (48, Key::KeyB),
+    (49, Key::KeyN),
+    (50, Key::KeyM),
+    (51, Key::Comma),
+    (52, Key::Dot),
+    (53, Key::Slash),
+    (54, Key::ShiftRight),
+    (55, Key::KpMultiply),
+    (56, Key::Alt),
+    (57, Key::Space),
+    (58, Key::CapsLock),
+    (59, Key::F1),
+    (60, Key::F2),
+    (61, Key::F3),
+    (62, Key::F4),
+    (63, Key::F5),
+    (64, Key::F6),
+    (65, Key::F7),
+    (66, Key::F8),
+    (67, Key::F9),
+    (68, Key::F10),
+    (69, Key::NumLock),
+    (70, Key::ScrollLock),
+    (71, Key::Kp7),
+    (72, Key::Kp8),
+    (73, Key::Kp9),
+    (74, Key::KpMinus),
+    (75, Key::Kp4),
+    (76, Key::Kp5),
+    (77, Key::Kp6),
+    (78, Key::KpPlus),
+    (79, Key::Kp1),
+    (80, Key::Kp2),
+    (81, Key::Kp3),
+    (82, Key::Kp0),
+    (83, Key::KpDelete),
+    (86, Key::IntlBackslash),
+    (87, Key::F11),
+    (88, Key::F12),
+    (96, Key::KpReturn),
+    (97, Key::ControlRight),
+    (98, Key::KpDivide),
+    (99, Key::PrintScreen),
+    (100, Key::AltGr),
+    (102, Key::Home),
+    (103, Key::UpArrow),
+    (104, Key::PageUp),
+    (105, Key::LeftArrow),
+    (106, Key::RightArrow),
+    (107, Key::End),
+    (108, Key::DownArrow),
+    (109, Key::PageDown),
+    (110, Key::Insert),
+    (111, Key::Delete),
+    (119, Key::Pause),
+    (125, Key::MetaLeft),
+    (126, Key::MetaRight)
+];
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct RawEvent {
+    pub kind: u16,
+    pub code: u16,
+    pub value: i32
+}
+
+impl RawEvent {
+
+    pub fn parse(bytes: &[u8; INPUT_EVENT_SIZE]) -> Self {
+        let fields = &bytes[TIMEVAL_SIZE..];
+        Self {
+            kind: u16::from_ne_bytes([fields[0], fields[1]]),
+            code: u16::from_ne_bytes([fields[2], fields[3]]),
+            value: i32::from_ne_bytes([fields[4], fields[5], fields[6], fields[7]])
+        }
+    }
+
+    pub fn to_event_type(self) -> Option<EventType> {
+        match (self.kind, self.value) {
+            // 0 is a release, 1 a press and 2 an autorepeat, which rdev also reports as a press
+            (EV_KEY, 0) => match button_from_code(self.code) {
+                Some(button) => Some(EventType::ButtonRelease(button)),
+                None if is_non_key_button(self.code) => None,
+                None => Some(EventType::KeyRelease(key_from_code(self.code)))
+            },
+            (EV_KEY, 1 | 2) => match button_from_code(self.code) {
+                Some(button) => Some(EventType::ButtonPress(button)),
+                None if is_non_key_button(self.code) => None,
+                None => Some(EventType::KeyPress(key_from_code(self.code)))
+            },
+            (EV_REL, value) if self.code == REL_WHEEL => Some(EventType::Wheel { delta_x: 0, delta_y: value as i64 }),
+            (EV_REL, value) if self.code == REL_HWHEEL => Some(EventType::Wheel { delta_x: value as i64, delta_y: 0 }),
+            _ => None
+        }
+    }
+}
+
+pub fn key_from_code(code: u16) -> Key {
+    KEYS.iter()
+        .find(|(c, _)| *c == code)
+        .map(|(_, key)| *key)
+        .unwrap_or(Key::Unknown(code as u32 + X_KEYCODE_OFFSET))
+}
+
+fn button_from_code(code: u16) -> Option<Button> {
+    match code {
+        BTN_LEFT => Some(Button::Left),
+        BTN_RIGHT => Some(Button::Right),
+        BTN_MIDDLE => Some(Button::Middle),
+        // Side buttons as the X server numbers them, from 8 up
+        code if code > BTN_MIDDLE && code <= BTN_TASK => Some(Button::Unknown((code - BTN_MIDDLE) as u8 + 7)),
+        _ => None
+    }
+}
+
+fn is_non_key_button(code: u16) -> bool {
+    NON_KEY_BUTTONS.iter().any(|range| range.contains(&code))
+}
+
+pub fn read_events<R, F>(mut reader: R, mut callback: F) -> io::Result<()>
+where
+    R: Read,
+    F: FnMut(EventType),
+{
+    let mut buffer = [0u8; INPUT_EVENT_SIZE];
+
+    loop {
+        match reader.read_exact(&mut buffer) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(err) => return Err(err)
+        }
+
+        if let Some(event_type) = RawEvent::parse(&buffer).to_event_type() {
+            callback(event_type);
+        }
+    }
+}
+
+pub fn listen(sender: Sender<(EventType, Instant)>) -> io::Result<()> {
+    let mut devices = 0;
+
+    for entry in fs::read_dir("/dev/input")? {
+        let path = entry?.path();
+        if !path.file_name().and_then(|name| name.to_str()).is_some_and(|name| name.starts_with("event")) {
+            continue;
+        }
+
+        // Devices we can't open are skipped, the user needs to be in the input group for the rest
+        let Ok(file) = File::open(&path) else {
+            continue;
+        };
+
+        let sender = sender.clone();
+        thread::spawn(move || {
+            if let Err(err) = read_events(file, |event_type| {
+                let _ = sender.send((event_type, clock::now()));
+            }) {
+                eprintln!("Error reading {}: {}", path.display(), err);
+            }
+        });
+
+        devices += 1;
+    }
+
+    if devices == 0 {
+        return Err(io::Error::new(io::ErrorKind::PermissionDenied, "no readable devices in /dev/input"));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EV_SYN: u16 = 0x00;
+    const EV_MSC: u16 = 0x04;
+    const MSC_SCAN: u16 = 0x04;
+
+    // evtest on an x86_64 keyboard: A pressed then released, each with its scan code and sync
+    #[cfg(all(target_pointer_width = "64", target_endian = "little"))]
+    const RECORDED_A: [u8; 6 * 24] = [
+        0x00, 0xf1, 0x53, 0x65, 0x00, 0x00, 0x00, 0x00, 0x40, 0xe2, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x00, 0x04, 0x00, 0x04, 0x00, 0x07, 0x00,
+        0x00, 0xf1, 0x53, 0x65, 0x00, 0x00, 0x00, 0x00, 0x40, 0xe2, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x1e, 0x00, 0x01, 0x00, 0x00, 0x00,
+        0x00, 0xf1, 0x53, 0x65, 0x00, 0x00, 0x00, 0x00, 0x40, 0xe2, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0xf1, 0x53, 0x65, 0x00, 0x00, 0x00, 0x00, 0x38, 0x6d, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x00, 0x04, 0x00, 0x04, 0x00, 0x07, 0x00,
+        0x00, 0xf1, 0x53, 0x65, 0x00, 0x00, 0x00, 0x00, 0x38, 0x6d, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x1e, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0xf1, 0x53, 0x65, 0x00, 0x00, 0x00, 0x00, 0x38, 0x6d, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00
+    ];
+
+    // Same layout as the kernel writes on the target the tests run on
+    fn event(kind: u16, code: u16, value: i32) -> Vec<u8> {
+        let mut bytes = vec![0; TIMEVAL_SIZE];
+        bytes.extend(kind.to_ne_bytes());
+        bytes.extend(code.to_ne_bytes());
+        bytes.extend(value.to_ne_bytes());
+        bytes
+    }
+
+    fn stream(events: &[(u16, u16, i32)]) -> Vec<u8> {
+        events.iter().flat_map(|(kind, code, value)| event(*kind, *code, *value)).collect()
+    }
+
+    fn read_all(bytes: &[u8]) -> Vec<EventType> {
+        let mut events = Vec::new();
+        read_events(bytes, |event_type| events.push(event_type)).unwrap();
+        events
+    }
+
+    #[test]
+    fn event_size_matches_the_target() {
+        assert_eq!(INPUT_EVENT_SIZE, if cfg!(target_pointer_width = "64") { 24 } else { 16 });
+    }
+
+    #[cfg(all(target_pointer_width = "64", target_endian = "little"))]
+    #[test]
+    fn reads_a_recorded_key_press() {
+        assert_eq!(read_all(&RECORDED_A), vec![EventType::KeyPress(Key::KeyA), EventType::KeyRelease(Key::KeyA)]);
+    }
+
+    #[test]
+    fn parses_the_fields_after_the_timestamp() {
+        let bytes: [u8; INPUT_EVENT_SIZE] = event(EV_REL, REL_WHEEL, -1).try_into().unwrap();
+        assert_eq!(RawEvent::parse(&bytes), RawEvent { kind: EV_REL, code: REL_WHEEL, value: -1 });
+    }
+
+    #[test]
+    fn autorepeat_is_a_press_and_sync_is_skipped() {
+        let bytes = stream(&[(EV_MSC, MSC_SCAN, 0x70005), (EV_KEY, 48, 1), (EV_SYN, 0, 0), (EV_KEY, 48, 2), (EV_KEY, 48, 0)]);
+        assert_eq!(read_all(&bytes), vec![EventType::KeyPress(Key::KeyB), EventType::KeyPress(Key::KeyB), EventType::KeyRelease(Key::KeyB)]);
+    }
+
+    #[test]
+    fn reads_mouse_buttons_and_wheels() {
+        let bytes = stream(&[(EV_KEY, BTN_LEFT, 1), (EV_KEY, 0x113, 0), (EV_REL, REL_WHEEL, 1), (EV_REL, REL_HWHEEL, -1), (EV_REL, 0x00, 5)]);
+        assert_eq!(read_all(&bytes), vec![
+            EventType::ButtonPress(Button::Left),
+            EventType::ButtonRelease(Button::Unknown(8)),
+            EventType::Wheel { delta_x: 0, delta_y: 1 },
+            EventType::Wheel { delta_x: -1, delta_y: 0 }
+        ]);
+    }
+
+    #[test]
+    fn ignores_touchpad_and_gamepad_buttons() {
+        // BTN_TOUCH, BTN_TOOL_FINGER, BTN_SOUTH, BTN_DPAD_UP and BTN_TRIGGER_HAPPY1
+        let bytes = stream(&[(EV_KEY, 0x14a, 1), (EV_KEY, 0x145, 1), (EV_KEY, 0x130, 1), (EV_KEY, 0x220, 0), (EV_KEY, 0x2c0, 1)]);
+        assert!(read_all(&bytes).is_empty());
+    }
+
+    #[test]
+    fn stops_at_a_truncated_event() {
+        let mut bytes = stream(&[(EV_KEY, 30, 1)]);
+        bytes.extend(&event(EV_KEY, 30, 0)[..INPUT_EVENT_SIZE / 2]);
+        assert_eq!(read_all(&bytes), vec![EventType::KeyPress(Key::KeyA)]);
+    }
+
+    #[test]
+    fn maps_codes_to_keys() {
+        assert_eq!(key_from_code(1), Key::Escape);
+        assert_eq!(key_from_code(30), Key::KeyA);
+        assert_eq!(key_from_code(126), Key::MetaRight);
+        // KEY_F13, which rdev names by its X keycode
+        assert_eq!(key_from_code(183), Key::Unknown(191));
+    }
+}
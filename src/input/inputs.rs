@@ -0,0 +1,159 @@
+use std::fmt;
+use rdev::{Button, Key};
+use crate::settings::keyboard_layout;
+use crate::utils::keys::{key_from_name, key_name};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    Extra(u8)
+}
+
+impl MouseButton {
+
+    pub fn from_rdev(button: Button) -> Option<Self> {
+        match button {
+            Button::Left => Some(MouseButton::Left),
+            Button::Right => Some(MouseButton::Right),
+            Button::Middle => Some(MouseButton::Middle),
+            #[cfg(windows)]
+            Button::Unknown(code) => Some(MouseButton::Extra(code)),
+            // X11 reports the wheel as buttons 4-7, side buttons start at 8
+            #[cfg(not(windows))]
+            Button::Unknown(code) if code >= 8 => Some(MouseButton::Extra(code - 7)),
+            #[cfg(not(windows))]
+            Button::Unknown(_) => None
+        }
+    }
+
+    pub fn from_gdk(button: u32) -> Option<Self> {
+        match button {
+            1 => Some(MouseButton::Left),
+            2 => Some(MouseButton::Middle),
+            3 => Some(MouseButton::Right),
+            #[cfg(windows)]
+            code if code >= 4 => Some(MouseButton::Extra((code - 3) as u8)),
+            #[cfg(not(windows))]
+            code if code >= 8 => Some(MouseButton::Extra((code - 7) as u8)),
+            _ => None
+        }
+    }
+
+    fn name(&self) -> String {
+        match self {
+            MouseButton::Left => String::from("MouseLeft"),
+            MouseButton::Right => String::from("MouseRight"),
+            MouseButton::Middle => String::from("MouseMiddle"),
+            MouseButton::Extra(n) => format!("Mouse{}", *n as u32 + 3)
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "MouseLeft" => Some(MouseButton::Left),
+            "MouseRight" => Some(MouseButton::Right),
+            "MouseMiddle" => Some(MouseButton::Middle),
+            _ => {
+                let n: u32 = name.strip_prefix("Mouse")?.parse().ok()?;
+                if !(4..=258).contains(&n) {
+                    return None;
+                }
+                Some(MouseButton::Extra((n - 3) as u8))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum WheelDirection {
+    Up,
+    Down,
+    Left,
+    Right
+}
+
+impl WheelDirection {
+
+    pub fn from_delta(delta_x: i64, delta_y: i64) -> Option<Self> {
+        if delta_y > 0 {
+            Some(WheelDirection::Up)
+        } else if delta_y < 0 {
+            Some(WheelDirection::Down)
+        } else if delta_x > 0 {
+            Some(WheelDirection::Right)
+        } else if delta_x < 0 {
+            Some(WheelDirection::Left)
+        } else {
+            None
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            WheelDirection::Up => "WheelUp",
+            WheelDirection::Down => "WheelDown",
+            WheelDirection::Left => "WheelLeft",
+            WheelDirection::Right => "WheelRight"
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "WheelUp" => Some(WheelDirection::Up),
+            "WheelDown" => Some(WheelDirection::Down),
+            "WheelLeft" => Some(WheelDirection::Left),
+            "WheelRight" => Some(WheelDirection::Right),
+            _ => None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Input {
+    Key(Key),
+    Mouse(MouseButton),
+    Wheel(WheelDirection)
+}
+
+impl Input {
+
+    pub fn parse(text: &str) -> Option<Self> {
+        if let Some(button) = MouseButton::from_name(text) {
+            return Some(Input::Mouse(button));
+        }
+
+        if let Some(direction) = WheelDirection::from_name(text) {
+            return Some(Input::Wheel(direction));
+        }
+
+        key_from_name(text).map(Input::Key)
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            Input::Key(key) => keyboard_layout().label(*key),
+            Input::Mouse(button) => button.name(),
+            Input::Wheel(direction) => direction.name().to_string()
+        }
+    }
+
+    pub fn as_key(&self) -> Option<Key> {
+        match self {
+            Input::Key(key) => Some(*key),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for Input {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Input::Key(key) => write!(f, "{}", key_name(*key)),
+            Input::Mouse(button) => write!(f, "{}", button.name()),
+            Input::Wheel(direction) => write!(f, "{}", direction.name())
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use std::collections::HashSet;
+use rdev::{EventType, Key};
+use crate::input::chord::Modifiers;
+
+#[derive(Debug, Default)]
+pub struct KeyState {
+    pressed: HashSet<Key>
+}
+
+impl KeyState {
+
+    pub fn new() -> Self {
+        Self {
+            pressed: HashSet::new()
+        }
+    }
+
+    pub fn update(&mut self, event_type: &EventType) -> Modifiers {
+        match event_type {
+            EventType::KeyPress(key) => {
+                let modifiers = self.modifiers_except(Some(*key));
+                self.pressed.insert(*key);
+                modifiers
+            }
+            EventType::KeyRelease(key) => {
+                self.pressed.remove(key);
+                self.modifiers_except(Some(*key))
+            }
+            _ => self.modifiers()
+        }
+    }
+
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers_except(None)
+    }
+
+    fn modifiers_except(&self, key: Option<Key>) -> Modifiers {
+        let mut modifiers = Modifiers::NONE;
+        for pressed in &self.pressed {
+            if Some(*pressed) == key {
+                continue;
+            }
+
+            if let Some(modifier) = Modifiers::from_key(*pressed) {
+                modifiers.insert(modifier);
+            }
+        }
+        modifiers
+    }
+}
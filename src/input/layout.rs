@@ -0,0 +1,254 @@
+use std::sync::RwLock;
+use rdev::Key;
+use crate::utils::keys::key_label;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Layout {
+    Us,
+    Azerty,
+    Qwertz
+}
+
+// (physical position, key Windows reports for it, label printed on it)
+const AZERTY: [(Key, Key, &str); 25] = [
+    (Key::BackQuote, Key::Quote, "\u{b2}"),
+    (Key::Num1, Key::Num1, "&"),
+    (Key::Num2, Key::Num2, "\u{e9}"),
+    (Key::Num3, Key::Num3, "\""),
+    (Key::Num4, Key::Num4, "'"),
+    (Key::Num5, Key::Num5, "("),
+    (Key::Num6, Key::Num6, "-"),
+    (Key::Num7, Key::Num7, "\u{e8}"),
+    (Key::Num8, Key::Num8, "_"),
+    (Key::Num9, Key::Num9, "\u{e7}"),
+    (Key::Num0, Key::Num0, "\u{e0}"),
+    (Key::Minus, Key::LeftBracket, ")"),
+    (Key::KeyQ, Key::KeyA, "A"),
+    (Key::KeyW, Key::KeyZ, "Z"),
+    (Key::LeftBracket, Key::RightBracket, "^"),
+    (Key::RightBracket, Key::SemiColon, "$"),
+    (Key::KeyA, Key::KeyQ, "Q"),
+    (Key::SemiColon, Key::KeyM, "M"),
+    (Key::Quote, Key::BackQuote, "\u{f9}"),
+    (Key::BackSlash, Key::BackSlash, "*"),
+    (Key::KeyZ, Key::KeyW, "W"),
+    (Key::KeyM, Key::Comma, ","),
+    (Key::Comma, Key::Dot, ";"),
+    (Key::Dot, Key::Slash, ":"),
+    (Key::Slash, Key::Unknown(0xDF), "!")
+];
+
+// German, the letters Y and Z swap and the punctuation moves
+const QWERTZ: [(Key, Key, &str); 11] = [
+    (Key::BackQuote, Key::BackSlash, "^"),
+    (Key::Minus, Key::LeftBracket, "\u{df}"),
+    (Key::Equal, Key::RightBracket, "\u{b4}"),
+    (Key::KeyY, Key::KeyZ, "Z"),
+    (Key::LeftBracket, Key::SemiColon, "\u{dc}"),
+    (Key::RightBracket, Key::Equal, "+"),
+    (Key::SemiColon, Key::BackQuote, "\u{d6}"),
+    (Key::Quote, Key::Quote, "\u{c4}"),
+    (Key::BackSlash, Key::Slash, "#"),
+    (Key::KeyZ, Key::KeyY, "Y"),
+    (Key::Slash, Key::Minus, "-")
+];
+
+// Asking Windows on every key is too slow for the hook, so detection is kept until refresh() looks again
+static DETECTED: RwLock<Option<Layout>> = RwLock::new(None);
+
+impl Layout {
+
+    pub const ALL: [Layout; 3] = [
+        Layout::Us,
+        Layout::Azerty,
+        Layout::Qwertz
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Layout::Us => "us",
+            Layout::Azerty => "azerty",
+            Layout::Qwertz => "qwertz"
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|layout| layout.name().eq_ignore_ascii_case(name))
+    }
+
+    fn table(&self) -> &'static [(Key, Key, &'static str)] {
+        match self {
+            Layout::Us => &[],
+            Layout::Azerty => &AZERTY,
+            Layout::Qwertz => &QWERTZ
+        }
+    }
+
+    // Bindings are stored by physical position, named after the US key in that spot
+    pub fn to_physical(self, reported: Key) -> Key {
+        self.table().iter()
+            .find(|(_, r, _)| *r == reported)
+            .map(|(physical, ..)| *physical)
+            .unwrap_or(reported)
+    }
+
+    #[cfg(test)]
+    pub fn to_reported(self, physical: Key) -> Key {
+        self.table().iter()
+            .find(|(p, ..)| *p == physical)
+            .map(|(_, reported, _)| *reported)
+            .unwrap_or(physical)
+    }
+
+    pub fn label(&self, physical: Key) -> String {
+        self.table().iter()
+            .find(|(p, ..)| *p == physical)
+            .map(|(.., label)| label.to_string())
+            .unwrap_or_else(|| key_label(physical))
+    }
+
+    // What typing the key gives when the layout moves it, for naming it by keysym
+    #[cfg(any(target_os = "linux", test))]
+    pub fn typed(&self, physical: Key) -> Option<char> {
+        let (.., label) = self.table().iter().find(|(p, ..)| *p == physical)?;
+        let mut chars = label.chars();
+
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => c.to_lowercase().next(),
+            _ => None
+        }
+    }
+
+    // Windows language id, as in the low word of a keyboard layout handle
+    pub fn from_language(lang_id: u16) -> Self {
+        match lang_id {
+            // French and Belgian French
+            0x040C | 0x080C => Layout::Azerty,
+            // German and Austrian German
+            0x0407 | 0x0C07 => Layout::Qwertz,
+            _ => Layout::Us
+        }
+    }
+
+    // POSIX locale such as fr_FR.UTF-8, mapped onto the same language ids
+    #[cfg(not(windows))]
+    pub fn from_locale(locale: &str) -> Self {
+        let lang_id = match locale.split(['.', '@']).next().unwrap_or_default() {
+            "fr_FR" => 0x040C,
+            "fr_BE" => 0x080C,
+            "de_DE" => 0x0407,
+            "de_AT" => 0x0C07,
+            _ => 0
+        };
+
+        Self::from_language(lang_id)
+    }
+
+    pub fn detected() -> Self {
+        if let Some(layout) = *DETECTED.read().unwrap() {
+            return layout;
+        }
+
+        let layout = Self::detect();
+        *DETECTED.write().unwrap() = Some(layout);
+        layout
+    }
+
+    // Looks again, true when the layout changed since it was last detected
+    pub fn refresh() -> bool {
+        let layout = Self::detect();
+        DETECTED.write().unwrap().replace(layout).is_some_and(|previous| previous != layout)
+    }
+
+    #[cfg(windows)]
+    fn detect() -> Self {
+        unsafe extern "system" {
+            fn GetForegroundWindow() -> *mut core::ffi::c_void;
+            fn GetWindowThreadProcessId(hWnd: *mut core::ffi::c_void, lpdwProcessId: *mut u32) -> u32;
+            fn GetKeyboardLayout(idThread: u32) -> *mut core::ffi::c_void;
+        }
+
+        // The layout of whatever has focus, usually the game
+        let hkl = unsafe {
+            let thread = GetWindowThreadProcessId(GetForegroundWindow(), core::ptr::null_mut());
+            GetKeyboardLayout(thread)
+        };
+
+        Self::from_language(hkl as usize as u16)
+    }
+
+    // There's no portable way to ask the desktop for its layout, the locale is the closest hint
+    #[cfg(not(windows))]
+    fn detect() -> Self {
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"].iter()
+            .find_map(|name| std::env::var(name).ok().filter(|value| !value.is_empty()))
+            .unwrap_or_default();
+
+        Self::from_locale(&locale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::keys::KEYS;
+
+    #[test]
+    fn every_key_round_trips() {
+        for layout in Layout::ALL {
+            for (key, ..) in KEYS {
+                assert_eq!(layout.to_physical(layout.to_reported(key)), key, "{:?} on {}", key, layout.name());
+            }
+        }
+    }
+
+    #[test]
+    fn azerty_moves_letters_and_keeps_the_rest() {
+        assert_eq!(Layout::Azerty.to_physical(Key::KeyA), Key::KeyQ);
+        assert_eq!(Layout::Azerty.to_reported(Key::KeyQ), Key::KeyA);
+        assert_eq!(Layout::Azerty.to_physical(Key::Unknown(0xDF)), Key::Slash);
+        assert_eq!(Layout::Azerty.to_physical(Key::F5), Key::F5);
+        assert_eq!(Layout::Us.to_physical(Key::KeyA), Key::KeyA);
+    }
+
+    #[test]
+    fn qwertz_swaps_y_and_z() {
+        assert_eq!(Layout::Qwertz.to_physical(Key::KeyZ), Key::KeyY);
+        assert_eq!(Layout::Qwertz.to_physical(Key::KeyY), Key::KeyZ);
+        assert_eq!(Layout::Qwertz.to_physical(Key::LeftBracket), Key::Minus);
+        assert_eq!(Layout::Qwertz.label(Key::SemiColon), "\u{d6}");
+    }
+
+    #[test]
+    fn labels_follow_the_layout() {
+        assert_eq!(Layout::Azerty.label(Key::KeyQ), "A");
+        assert_eq!(Layout::Azerty.label(Key::Num2), "\u{e9}");
+        assert_eq!(Layout::Us.label(Key::KeyQ), "Q");
+        assert_eq!(Layout::Azerty.label(Key::Space), Layout::Us.label(Key::Space));
+    }
+
+    #[test]
+    fn typed_characters_follow_the_layout() {
+        assert_eq!(Layout::Azerty.typed(Key::Num2), Some('\u{e9}'));
+        assert_eq!(Layout::Azerty.typed(Key::KeyQ), Some('a'));
+        assert_eq!(Layout::Qwertz.typed(Key::LeftBracket), Some('\u{fc}'));
+        assert_eq!(Layout::Us.typed(Key::KeyQ), None);
+    }
+
+    #[test]
+    fn detects_from_language() {
+        assert_eq!(Layout::from_language(0x040C), Layout::Azerty);
+        assert_eq!(Layout::from_language(0x080C), Layout::Azerty);
+        assert_eq!(Layout::from_language(0x0407), Layout::Qwertz);
+        assert_eq!(Layout::from_language(0x0409), Layout::Us);
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn detects_from_locale() {
+        assert_eq!(Layout::from_locale("fr_BE.UTF-8"), Layout::Azerty);
+        assert_eq!(Layout::from_locale("de_AT.UTF-8"), Layout::Qwertz);
+        assert_eq!(Layout::from_locale("fr_CA.UTF-8"), Layout::Us);
+        assert_eq!(Layout::from_locale(""), Layout::Us);
+    }
+}
@@ -0,0 +1,124 @@
+use std::sync::mpsc;
+use std::thread;
+use std::time::Instant;
+use rdev::{listen, EventType, Key};
+use crate::bus::event_bus::send_event;
+use crate::bus::events::button_event::{ButtonEvent, ButtonState, InputSource};
+#[cfg(target_os = "linux")]
+use crate::input::{evdev, portal};
+use crate::input::inputs::{Input, MouseButton, WheelDirection};
+use crate::input::key_state::KeyState;
+use crate::utils::clock;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum InputBackend {
+    Rdev,
+    // Reads /dev/input directly, for Wayland sessions where rdev sees nothing
+    #[cfg(target_os = "linux")]
+    Evdev,
+    // Shortcuts registered with the desktop, for sandboxed Wayland sessions
+    #[cfg(target_os = "linux")]
+    Portal
+}
+
+impl InputBackend {
+
+    pub const ALL: &[InputBackend] = &[
+        InputBackend::Rdev,
+        #[cfg(target_os = "linux")]
+        InputBackend::Evdev,
+        #[cfg(target_os = "linux")]
+        InputBackend::Portal
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            InputBackend::Rdev => "rdev",
+            #[cfg(target_os = "linux")]
+            InputBackend::Evdev => "evdev",
+            #[cfg(target_os = "linux")]
+            InputBackend::Portal => "portal"
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|backend| backend.name().eq_ignore_ascii_case(name))
+    }
+}
+
+pub fn start(backend: InputBackend) {
+    // The portal talks D-Bus on the main loop and sends its own events
+    #[cfg(target_os = "linux")]
+    if backend == InputBackend::Portal {
+        match portal::connect() {
+            Ok(connection) => portal::start(&connection),
+            Err(err) => eprintln!("Error: {}", err)
+        }
+        return;
+    }
+
+    let (sender, receiver) = mpsc::channel::<(EventType, Instant)>();
+
+    thread::spawn(move || {
+        let mut key_state = KeyState::new();
+        for (event_type, time) in receiver {
+            emit(&mut key_state, event_type, time);
+        }
+    });
+
+    thread::spawn(move || {
+        let result = match backend {
+            // Stamped in the rdev callback, so a busy consumer thread can't delay the capture time
+            InputBackend::Rdev => listen(move |event| {
+                let _ = sender.send((event.event_type, clock::now()));
+            }).map_err(|err| format!("{:?}", err)),
+            #[cfg(target_os = "linux")]
+            InputBackend::Evdev => evdev::listen(sender).map_err(|err| err.to_string()),
+            #[cfg(target_os = "linux")]
+            InputBackend::Portal => Ok(())
+        };
+
+        if let Err(err) = result {
+            eprintln!("Error: {}", err);
+        }
+    });
+}
+
+fn emit(key_state: &mut KeyState, event_type: EventType, time: Instant) {
+    let modifiers = key_state.update(&event_type);
+
+    match event_type {
+        EventType::KeyPress(key) => {
+            send_event(Box::new(ButtonEvent::new(Input::Key(physical_key(key)), modifiers, ButtonState::Pressed, InputSource::Keyboard, time)))
+        }
+        EventType::KeyRelease(key) => {
+            send_event(Box::new(ButtonEvent::new(Input::Key(physical_key(key)), modifiers, ButtonState::Released, InputSource::Keyboard, time)))
+        }
+        EventType::ButtonPress(button) => {
+            if let Some(button) = MouseButton::from_rdev(button) {
+                send_event(Box::new(ButtonEvent::new(Input::Mouse(button), modifiers, ButtonState::Pressed, InputSource::Mouse, time)))
+            }
+        }
+        EventType::ButtonRelease(button) => {
+            if let Some(button) = MouseButton::from_rdev(button) {
+                send_event(Box::new(ButtonEvent::new(Input::Mouse(button), modifiers, ButtonState::Released, InputSource::Mouse, time)))
+            }
+        }
+        EventType::Wheel { delta_x, delta_y } => {
+            if let Some(direction) = WheelDirection::from_delta(delta_x, delta_y) {
+                send_event(Box::new(ButtonEvent::new(Input::Wheel(direction), modifiers, ButtonState::Pressed, InputSource::Mouse, time)));
+                send_event(Box::new(ButtonEvent::new(Input::Wheel(direction), modifiers, ButtonState::Released, InputSource::Mouse, time)));
+            }
+        }
+        _ => {}
+    }
+}
+
+// rdev names keys by the Windows virtual key, which moves with the layout; elsewhere it reports the position
+fn physical_key(key: Key) -> Key {
+    if cfg!(windows) {
+        crate::settings::keyboard_layout().to_physical(key)
+    } else {
+        key
+    }
+}
@@ -0,0 +1,14 @@
+pub mod action;
+pub mod bindings;
+pub mod capture;
+pub mod chord;
+#[cfg(target_os = "linux")]
+pub mod evdev;
+pub mod inputs;
+pub mod key_state;
+pub mod layout;
+pub mod listener;
+#[cfg(target_os = "linux")]
+pub mod portal;
+pub mod router;
+pub mod trigger;
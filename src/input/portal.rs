@@ -0,0 +1,493 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use gtk4::{gdk, gio};
+use gtk4::glib;
+use gtk4::glib::prelude::ToVariant;
+use gtk4::glib::translate::FromGlib;
+use gtk4::glib::variant::ObjectPath;
+use crate::bus::event_bus::{register_event, send_event};
+use crate::bus::event_bus::EventPropagation::Continue;
+use crate::bus::events::button_event::{ButtonEvent, ButtonState, InputSource};
+use crate::bus::events::settings_event::SettingsEvent;
+use crate::input::action::Action;
+use crate::input::bindings::Bindings;
+use crate::input::chord::{Chord, Modifiers};
+use crate::input::layout::Layout;
+use crate::settings::{keyboard_layout, with_bindings};
+use crate::utils::clock;
+use crate::utils::keys::key_to_gdk;
+
+const PORTAL_BUS: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const SHORTCUTS_INTERFACE: &str = "org.freedesktop.portal.GlobalShortcuts";
+const REQUEST_INTERFACE: &str = "org.freedesktop.portal.Request";
+
+const SESSION_TOKEN: &str = "phastimer";
+const CREATE_SESSION_TOKEN: &str = "phastimer_create_session";
+const BIND_SHORTCUTS_TOKEN: &str = "phastimer_bind_shortcuts";
+
+pub fn connect() -> Result<gio::DBusConnection, glib::Error> {
+    gio::bus_get_sync(gio::BusType::Session, None::<&gio::Cancellable>)
+}
+
+// The a(sa{sv}) that BindShortcuts takes: shortcut id and its properties
+pub type Shortcuts = Vec<(String, HashMap<String, glib::Variant>)>;
+
+// Alive for the rest of the run once the portal hands out a session
+struct Session {
+    connection: gio::DBusConnection,
+    handle: String,
+    // What the desktop was last sent, so unrelated settings changes don't bind again
+    bound: RefCell<Option<Shortcuts>>,
+    requests: Cell<u32>,
+    _signals: Vec<gio::SignalSubscription>
+}
+
+// Takes the connection so it can be pointed at a private bus with a mock portal
+pub fn start(connection: &gio::DBusConnection) {
+    let Some(create_path) = request_path(connection, CREATE_SESSION_TOKEN) else {
+        eprintln!("Error: portal connection has no unique name");
+        return;
+    };
+
+    on_response(connection, &create_path, {
+        let connection = connection.clone();
+        move |results| {
+            let Some(handle) = results.get("session_handle").and_then(|handle| handle.str().map(String::from)) else {
+                eprintln!("Error: GlobalShortcuts portal didn't return a session");
+                return;
+            };
+
+            let session = Rc::new(Session {
+                _signals: subscribe_shortcut_signals(&connection, &handle),
+                connection: connection.clone(),
+                handle,
+                bound: RefCell::new(None),
+                requests: Cell::new(0)
+            });
+
+            with_bindings(|bindings| bind_shortcuts(&session, bindings, keyboard_layout()));
+
+            // The desktop only knows what it was last sent, so edits from the console or the file go out again
+            register_event("settings_event", move |_, event| {
+                let event = event.as_any().downcast_ref::<SettingsEvent>().unwrap();
+                let layout = event.settings.keyboard_layout.unwrap_or_else(Layout::detected);
+                bind_shortcuts(&session, &event.settings.bindings, layout);
+                Continue
+            }, false).detach();
+        }
+    });
+
+    let mut options = HashMap::new();
+    options.insert(String::from("handle_token"), CREATE_SESSION_TOKEN.to_variant());
+    options.insert(String::from("session_handle_token"), SESSION_TOKEN.to_variant());
+
+    call_portal(connection, "CreateSession", (options,).to_variant());
+}
+
+fn bind_shortcuts(session: &Session, bindings: &Bindings, layout: Layout) {
+    let shortcuts = shortcuts(bindings, layout);
+    if session.bound.borrow().as_ref() == Some(&shortcuts) {
+        return;
+    }
+
+    let Ok(handle) = ObjectPath::try_from(session.handle.clone()) else {
+        return;
+    };
+
+    // A fresh token each time, an earlier request may not have answered yet
+    let token = format!("{}_{}", BIND_SHORTCUTS_TOKEN, session.requests.get());
+    session.requests.set(session.requests.get() + 1);
+
+    let Some(bind_path) = request_path(&session.connection, &token) else {
+        return;
+    };
+
+    on_response(&session.connection, &bind_path, |_| {});
+
+    let mut options = HashMap::new();
+    options.insert(String::from("handle_token"), token.to_variant());
+
+    let parameters = (handle, shortcuts.clone(), String::new(), options).to_variant();
+    call_portal(&session.connection, "BindShortcuts", parameters);
+
+    *session.bound.borrow_mut() = Some(shortcuts);
+}
+
+fn subscribe_shortcut_signals(connection: &gio::DBusConnection, session: &str) -> Vec<gio::SignalSubscription> {
+    let signal_clock = Rc::new(RefCell::new(SignalClock::default()));
+
+    [("Activated", ButtonState::Pressed), ("Deactivated", ButtonState::Released)].into_iter()
+        .map(|(member, state)| {
+            let session = session.to_string();
+            let signal_clock = signal_clock.clone();
+            connection.subscribe_to_signal(Some(PORTAL_BUS), Some(SHORTCUTS_INTERFACE), Some(member), Some(PORTAL_PATH), None, gio::DBusSignalFlags::NONE, move |signal| {
+                // (o session_handle, s shortcut_id, t timestamp, a{sv} options), and every session on the desktop hears them
+                if signal.parameters.try_child_value(0).as_ref().and_then(|handle| handle.str()) != Some(session.as_str()) {
+                    return;
+                }
+
+                let Some(shortcut_id) = signal.parameters.try_child_value(1).and_then(|id| id.get::<String>()) else {
+                    return;
+                };
+
+                let timestamp = signal.parameters.try_child_value(2).and_then(|timestamp| timestamp.get::<u64>()).unwrap_or(0);
+                let time = signal_clock.borrow_mut().instant(timestamp);
+
+                if let Some(event) = with_bindings(|bindings| button_event(bindings, &shortcut_id, state, time)) {
+                    send_event(Box::new(event));
+                }
+            })
+        })
+        .collect()
+}
+
+// The desktop stamps presses in milliseconds from a clock of its own, so they're pinned to ours
+// at the signal that arrived soonest after its press and spaced out from there
+#[derive(Default)]
+struct SignalClock {
+    anchor: Option<(u64, Instant)>
+}
+
+impl SignalClock {
+
+    fn instant(&mut self, timestamp: u64) -> Instant {
+        let now = clock::now();
+
+        // Zero is a desktop that doesn't stamp them
+        if timestamp == 0 {
+            return now;
+        }
+
+        if let Some((anchor_timestamp, anchor)) = self.anchor
+            && let Some(since) = timestamp.checked_sub(anchor_timestamp)
+            && let Some(time) = anchor.checked_add(Duration::from_millis(since))
+            && time <= now {
+            return time;
+        }
+
+        // Either the first, or it came through quicker than the one pinned before
+        self.anchor = Some((timestamp, now));
+        now
+    }
+}
+
+fn on_response<F>(connection: &gio::DBusConnection, path: &str, callback: F)
+where
+    F: Fn(HashMap<String, glib::Variant>) + 'static,
+{
+    // Each request answers once, so the subscription drops itself
+    let subscription = Rc::new(RefCell::new(None));
+
+    *subscription.borrow_mut() = Some(connection.subscribe_to_signal(Some(PORTAL_BUS), Some(REQUEST_INTERFACE), Some("Response"), Some(path), None, gio::DBusSignalFlags::NONE, {
+        let subscription = subscription.clone();
+        move |signal| {
+            drop(subscription.borrow_mut().take());
+
+            match signal.parameters.get::<(u32, HashMap<String, glib::Variant>)>() {
+                Some((0, results)) => callback(results),
+                Some((response, _)) => eprintln!("Error: GlobalShortcuts request was refused ({})", response),
+                None => eprintln!("Error: malformed GlobalShortcuts response")
+            }
+        }
+    }));
+}
+
+fn call_portal(connection: &gio::DBusConnection, method: &'static str, parameters: glib::Variant) {
+    connection.call(Some(PORTAL_BUS), PORTAL_PATH, SHORTCUTS_INTERFACE, method, Some(&parameters), None, gio::DBusCallFlags::NONE, -1, None::<&gio::Cancellable>, move |result| {
+        if let Err(err) = result {
+            eprintln!("Error: GlobalShortcuts {} failed: {}", method, err);
+        }
+    });
+}
+
+// The portal announces the Response on a path derived from our unique name and the token
+pub fn request_path(connection: &gio::DBusConnection, token: &str) -> Option<String> {
+    let sender = connection.unique_name()?;
+    Some(format!("{}/request/{}/{}", PORTAL_PATH, sender.trim_start_matches(':').replace('.', "_"), token))
+}
+
+pub fn shortcuts(bindings: &Bindings, layout: Layout) -> Shortcuts {
+    Action::ALL.iter()
+        .filter(|action| !bindings.bindings_for(**action).is_empty())
+        .map(|action| {
+            let mut properties = HashMap::new();
+            properties.insert(String::from("description"), action.name().replace('_', " ").to_variant());
+
+            if let Some(trigger) = bindings.bindings_for(*action).first().and_then(|binding| preferred_trigger(&binding.chord, layout)) {
+                properties.insert(String::from("preferred_trigger"), trigger.to_variant());
+            }
+
+            (action.name().to_string(), properties)
+        })
+        .collect()
+}
+
+// XDG shortcut format, e.g. CTRL+SHIFT+KP_1, named by the keysym the layout types at that position
+pub fn preferred_trigger(chord: &Chord, layout: Layout) -> Option<String> {
+    let key = chord.input.as_key()?;
+    let keyval = match layout.typed(key) {
+        // Every code point has a keyval, so any u32 gdk hands back is a valid one
+        Some(c) => unsafe { gdk::Key::from_glib(gdk::unicode_to_keyval(c as u32)) },
+        None => key_to_gdk(key)?
+    };
+    let keysym = keyval.name()?;
+
+    let mut trigger = String::new();
+    for (modifier, name) in [(Modifiers::CTRL, "CTRL"), (Modifiers::SHIFT, "SHIFT"), (Modifiers::ALT, "ALT"), (Modifiers::META, "LOGO")] {
+        if chord.modifiers.contains(modifier) {
+            trigger.push_str(name);
+            trigger.push('+');
+        }
+    }
+
+    trigger.push_str(&keysym);
+    Some(trigger)
+}
+
+// Replays the action's own binding so triggers and the lock still go through the router
+pub fn button_event(bindings: &Bindings, shortcut_id: &str, state: ButtonState, time: Instant) -> Option<ButtonEvent> {
+    let action = Action::from_name(shortcut_id)?;
+    let chord = bindings.bindings_for(action).first()?.chord;
+
+    Some(ButtonEvent::new(chord.input, chord.modifiers, state, InputSource::Portal, time))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader};
+    use std::process::{Child, Command, Stdio};
+    use std::sync::Arc;
+    use super::*;
+    use crate::bus::event_bus::tests::test_lock;
+    use crate::bus::subscription::Subscription;
+    use crate::input::bindings::Binding;
+    use crate::input::inputs::Input;
+    use crate::settings::{self, Settings};
+    use crate::utils::clock::FakeClock;
+    use rdev::Key;
+
+    const MOCK_INTERFACE: &str = r#"
+        <node>
+            <interface name="org.freedesktop.portal.GlobalShortcuts">
+                <method name="CreateSession">
+                    <arg type="a{sv}" direction="in"/>
+                    <arg type="o" direction="out"/>
+                </method>
+                <method name="BindShortcuts">
+                    <arg type="o" direction="in"/>
+                    <arg type="a(sa{sv})" direction="in"/>
+                    <arg type="s" direction="in"/>
+                    <arg type="a{sv}" direction="in"/>
+                    <arg type="o" direction="out"/>
+                </method>
+            </interface>
+        </node>
+    "#;
+
+    // A bus of its own, so the test never reaches the desktop's real portal
+    struct PrivateBus {
+        daemon: Child,
+        address: String
+    }
+
+    impl PrivateBus {
+
+        fn start() -> Option<Self> {
+            let mut daemon = Command::new("dbus-daemon")
+                .args(["--session", "--nofork", "--print-address"])
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()
+                .ok()?;
+
+            let mut address = String::new();
+            BufReader::new(daemon.stdout.take()?).read_line(&mut address).ok()?;
+
+            Some(Self {
+                daemon,
+                address: address.trim().to_string()
+            })
+        }
+
+        fn connect(&self) -> gio::DBusConnection {
+            let flags = gio::DBusConnectionFlags::AUTHENTICATION_CLIENT | gio::DBusConnectionFlags::MESSAGE_BUS_CONNECTION;
+            gio::DBusConnection::for_address_sync(&self.address, flags, None, None::<&gio::Cancellable>).unwrap()
+        }
+    }
+
+    impl Drop for PrivateBus {
+
+        fn drop(&mut self) {
+            let _ = self.daemon.kill();
+            let _ = self.daemon.wait();
+        }
+    }
+
+    type Bind = Vec<(String, Option<String>)>;
+
+    // Answers like xdg-desktop-portal and remembers every BindShortcuts as (id, preferred trigger) pairs
+    struct MockPortal {
+        connection: gio::DBusConnection,
+        session: Rc<RefCell<Option<String>>>,
+        binds: Rc<RefCell<Vec<Bind>>>
+    }
+
+    impl MockPortal {
+
+        fn start(bus: &PrivateBus) -> Self {
+            let connection = bus.connect();
+            connection.call_sync(Some("org.freedesktop.DBus"), "/org/freedesktop/DBus", "org.freedesktop.DBus", "RequestName", Some(&(PORTAL_BUS, 4u32).to_variant()), None, gio::DBusCallFlags::NONE, -1, None::<&gio::Cancellable>).unwrap();
+
+            let session = Rc::new(RefCell::new(None));
+            let binds = Rc::new(RefCell::new(Vec::new()));
+
+            let info = gio::DBusNodeInfo::for_xml(MOCK_INTERFACE).unwrap();
+            connection.register_object(PORTAL_PATH, &info.lookup_interface(SHORTCUTS_INTERFACE).unwrap())
+                .method_call({
+                    let session = session.clone();
+                    let binds = binds.clone();
+                    move |connection, sender, _, _, method, parameters, invocation| {
+                        let sender = sender.unwrap().trim_start_matches(':').replace('.', "_");
+                        let options = parameters.try_child_value(parameters.n_children() - 1).unwrap().get::<HashMap<String, glib::Variant>>().unwrap();
+                        let token = options["handle_token"].get::<String>().unwrap();
+                        let request = format!("{}/request/{}/{}", PORTAL_PATH, sender, token);
+
+                        let mut results = HashMap::<String, glib::Variant>::new();
+                        if method == "CreateSession" {
+                            let handle = format!("{}/session/{}/{}", PORTAL_PATH, sender, options["session_handle_token"].get::<String>().unwrap());
+                            results.insert(String::from("session_handle"), handle.to_variant());
+                            *session.borrow_mut() = Some(handle);
+                        } else {
+                            let shortcuts = parameters.child_value(1).get::<Shortcuts>().unwrap();
+                            binds.borrow_mut().push(shortcuts.into_iter()
+                                .map(|(id, properties)| (id, properties.get("preferred_trigger").and_then(|trigger| trigger.get::<String>())))
+                                .collect());
+                        }
+
+                        invocation.return_value(Some(&(ObjectPath::try_from(request.clone()).unwrap(),).to_variant()));
+                        connection.emit_signal(None, &request, REQUEST_INTERFACE, "Response", Some(&(0u32, results).to_variant())).unwrap();
+                    }
+                })
+                .build()
+                .unwrap();
+
+            Self {
+                connection,
+                session,
+                binds
+            }
+        }
+
+        fn activate(&self, session: &str, shortcut_id: &str, member: &str, timestamp: u64) {
+            let parameters = (ObjectPath::try_from(session.to_string()).unwrap(), shortcut_id, timestamp, HashMap::<String, glib::Variant>::new()).to_variant();
+            self.connection.emit_signal(None, PORTAL_PATH, SHORTCUTS_INTERFACE, member, Some(&parameters)).unwrap();
+        }
+    }
+
+    fn run_until(condition: impl Fn() -> bool) {
+        let context = glib::MainContext::default();
+        let deadline = Instant::now() + Duration::from_secs(5);
+
+        while !condition() {
+            assert!(Instant::now() < deadline, "timed out waiting on the portal");
+            if !context.iteration(false) {
+                std::thread::sleep(Duration::from_millis(5));
+            }
+        }
+    }
+
+    fn settings_with(smudge_start: &str) -> Settings {
+        let mut settings = Settings {
+            keyboard_layout: Some(Layout::Us),
+            ..Settings::default()
+        };
+        settings.bindings.set(Action::SmudgeStart, &[Binding::parse(smudge_start).unwrap()]);
+        settings
+    }
+
+    #[test]
+    fn preferred_triggers_name_what_the_layout_types() {
+        let trigger = |binding: &str, layout| preferred_trigger(&Binding::parse(binding).unwrap().chord, layout);
+        assert_eq!(trigger("KeyQ", Layout::Us).as_deref(), Some("q"));
+        assert_eq!(trigger("KeyQ", Layout::Azerty).as_deref(), Some("a"));
+        assert_eq!(trigger("Ctrl+Num2", Layout::Azerty).as_deref(), Some("CTRL+eacute"));
+        assert_eq!(trigger("KeyZ", Layout::Qwertz).as_deref(), Some("y"));
+    }
+
+    #[test]
+    fn signal_times_keep_the_desktops_spacing() {
+        let _lock = test_lock();
+        let fake = Arc::new(FakeClock::default());
+        clock::set_clock(fake.clone());
+        let start = clock::now();
+        let mut signal_clock = SignalClock::default();
+
+        assert_eq!(signal_clock.instant(1000), start);
+
+        // Released 120ms after the press, but heard 150ms after it
+        fake.advance(Duration::from_millis(150));
+        assert_eq!(signal_clock.instant(1120), start + Duration::from_millis(120));
+
+        // Can't be later than it arrived, so this one is pinned instead
+        assert_eq!(signal_clock.instant(1200), start + Duration::from_millis(150));
+        fake.advance(Duration::from_millis(100));
+        assert_eq!(signal_clock.instant(1290), start + Duration::from_millis(240));
+
+        assert_eq!(signal_clock.instant(0), clock::now());
+        clock::reset_clock();
+    }
+
+    #[test]
+    #[ignore = "starts a private dbus-daemon"]
+    fn binds_forwards_its_own_shortcuts_and_rebinds_on_change() {
+        let _lock = test_lock();
+        let bus = PrivateBus::start().expect("dbus-daemon isn't available");
+
+        settings::replace(settings_with("KeyQ"));
+        let portal = MockPortal::start(&bus);
+
+        let pressed = Rc::new(RefCell::new(Vec::new()));
+        let _listener: Subscription = register_event("button_event", {
+            let pressed = pressed.clone();
+            move |_, event| {
+                let event = event.as_any().downcast_ref::<ButtonEvent>().unwrap();
+                pressed.borrow_mut().push((event.button, event.state, event.source));
+                Continue
+            }
+        }, false);
+
+        let client = bus.connect();
+        start(&client);
+        run_until(|| portal.binds.borrow().len() == 1);
+
+        let first = portal.binds.borrow()[0].clone();
+        assert!(first.contains(&(String::from("smudge_start"), Some(String::from("q")))));
+        assert!(first.iter().any(|(id, _)| id == "open_console"));
+
+        // Another app's session on the same desktop, then ours
+        let session = portal.session.borrow().clone().unwrap();
+        portal.activate("/org/freedesktop/portal/desktop/session/other/app", "smudge_start", "Activated", 1000);
+        portal.activate(&session, "smudge_start", "Activated", 1000);
+        portal.activate(&session, "smudge_start", "Deactivated", 1120);
+        run_until(|| pressed.borrow().len() == 2);
+        assert_eq!(*pressed.borrow(), vec![
+            (Input::Key(Key::KeyQ), ButtonState::Pressed, InputSource::Portal),
+            (Input::Key(Key::KeyQ), ButtonState::Released, InputSource::Portal)
+        ]);
+
+        // A change that doesn't touch the shortcuts isn't sent again
+        let mut unrelated = settings_with("KeyQ");
+        unrelated.ghost_speed = 4;
+        send_event(Box::new(SettingsEvent::new(unrelated)));
+
+        settings::replace(settings_with("KeyW"));
+        send_event(Box::new(SettingsEvent::new(settings::current())));
+        run_until(|| portal.binds.borrow().len() == 2);
+
+        assert!(portal.binds.borrow()[1].contains(&(String::from("smudge_start"), Some(String::from("w")))));
+        assert_eq!(portal.binds.borrow().len(), 2);
+    }
+}
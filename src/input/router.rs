@@ -0,0 +1,424 @@
+use std::cell::{Cell, RefCell};
+use std::time::{Duration, Instant};
+use crate::bus::event_bus::{register_event, send_event, send_event_at, ScheduleHandle};
+use crate::bus::event_bus::EventPropagation::Continue;
+use crate::bus::events::action_event::ActionEvent;
+use crate::bus::events::button_event::{ButtonEvent, InputSource};
+use crate::bus::events::hold_event::HoldEvent;
+use crate::bus::events::hold_progress_event::HoldProgressEvent;
+use crate::bus::events::lock_event::LockEvent;
+use crate::bus::subscription::Subscription;
+use crate::input::action::Action;
+use crate::input::bindings::Binding;
+use crate::input::inputs::Input;
+use crate::input::trigger::Trigger;
+use rdev::Key;
+use crate::settings::{auto_lock, double_tap_window, with_bindings};
+
+struct PendingHold {
+    input: Input,
+    action: Action,
+    duration: Duration,
+    handle: ScheduleHandle,
+    fired: bool
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum LockState {
+    Unlocked,
+    Locked,
+    // Locked by the chat key, released again when the chat is sent or closed
+    Chat
+}
+
+// The binding an input resolved to, if any
+type Resolved = Option<(Binding, Action)>;
+
+const CHAT_OPEN_KEY: Key = Key::Return;
+const CHAT_CLOSE_KEYS: [Key; 2] = [Key::Return, Key::Escape];
+
+thread_local! {
+    static LOCK: Cell<LockState> = const { Cell::new(LockState::Unlocked) };
+    static PENDING_HOLD: RefCell<Option<PendingHold>> = const { RefCell::new(None) };
+    static LAST_TAP: RefCell<Option<(Binding, Instant)>> = const { RefCell::new(None) };
+    // What each held input resolved to when it went down
+    static PRESSED: RefCell<Vec<(Input, Resolved)>> = const { RefCell::new(Vec::new()) };
+}
+
+pub fn init() -> Vec<Subscription> {
+    let button_listener = register_event("button_event", |_, event| {
+        let event = event.as_any().downcast_ref::<ButtonEvent>().unwrap();
+
+        if event.is_pressed() {
+            on_pressed(event);
+        } else {
+            on_released(event);
+        }
+
+        Continue
+    }, false);
+
+    let hold_listener = register_event("hold_event", |_, event| {
+        let event = event.as_any().downcast_ref::<HoldEvent>().unwrap();
+
+        let duration = PENDING_HOLD.with(|pending| {
+            let mut pending = pending.borrow_mut();
+            match pending.as_mut() {
+                Some(hold) if hold.action == event.action && !hold.fired => {
+                    hold.fired = true;
+                    Some(hold.duration)
+                }
+                _ => None
+            }
+        });
+
+        if let Some(duration) = duration {
+            send_event(Box::new(HoldProgressEvent::new(None, duration)));
+            fire(event.action, event.source, event.time + duration);
+        }
+
+        Continue
+    }, false);
+
+    vec![button_listener, hold_listener]
+}
+
+fn on_pressed(event: &ButtonEvent) {
+    // Auto-repeat keeps sending presses while a key is held
+    if PENDING_HOLD.with(|pending| pending.borrow().as_ref().is_some_and(|hold| hold.input == event.button)) {
+        return;
+    }
+
+    // Resolved once per press, so the chord stays the one held down whichever key comes up first
+    let resolved = PRESSED.with(|pressed| {
+        let mut pressed = pressed.borrow_mut();
+        if let Some((_, resolved)) = pressed.iter().find(|(input, _)| *input == event.button) {
+            return *resolved;
+        }
+
+        let resolved = with_bindings(|bindings| bindings.binding_for(event.modifiers, event.button));
+        pressed.push((event.button, resolved));
+        resolved
+    });
+
+    let Some((binding, action)) = resolved else {
+        return;
+    };
+
+    if is_locked() && action != Action::ToggleLock {
+        return;
+    }
+
+    let Trigger::Hold(duration) = binding.trigger else {
+        return;
+    };
+
+    cancel_hold();
+
+    // From the capture time, so a busy main loop doesn't stretch the hold
+    let handle = send_event_at(event.time + duration, Box::new(HoldEvent::new(action, event.source, event.time)));
+    PENDING_HOLD.with(|pending| {
+        *pending.borrow_mut() = Some(PendingHold {
+            input: event.button,
+            action,
+            duration,
+            handle,
+            fired: false
+        });
+    });
+
+    send_event(Box::new(HoldProgressEvent::new(Some(event.time), duration)));
+}
+
+fn on_released(event: &ButtonEvent) {
+    let pressed = PRESSED.with(|pressed| {
+        let mut pressed = pressed.borrow_mut();
+        let position = pressed.iter().position(|(input, _)| *input == event.button)?;
+        Some(pressed.remove(position).1)
+    });
+
+    if PENDING_HOLD.with(|pending| pending.borrow().as_ref().is_some_and(|hold| hold.input == event.button)) {
+        cancel_hold();
+        return;
+    }
+
+    if LOCK.get() == LockState::Chat && CHAT_CLOSE_KEYS.iter().any(|key| event.button == Input::Key(*key)) {
+        set_lock(LockState::Unlocked);
+        return;
+    }
+
+    if LOCK.get() == LockState::Unlocked && auto_lock() && event.button == Input::Key(CHAT_OPEN_KEY) {
+        set_lock(LockState::Chat);
+        return;
+    }
+
+    // Only a key held since before the listener started comes up without a press we saw
+    let resolved = pressed.unwrap_or_else(|| with_bindings(|bindings| bindings.binding_for(event.modifiers, event.button)));
+    let Some((binding, action)) = resolved else {
+        return;
+    };
+
+    if is_locked() && action != Action::ToggleLock {
+        return;
+    }
+
+    match binding.trigger {
+        Trigger::Tap => {
+            fire(action, event.source, event.time);
+        }
+        Trigger::DoubleTap => {
+            let doubled = LAST_TAP.with(|last| {
+                let mut last = last.borrow_mut();
+                match last.take() {
+                    Some((previous, time)) if previous == binding && event.time.saturating_duration_since(time) <= double_tap_window() => true,
+                    _ => {
+                        *last = Some((binding, event.time));
+                        false
+                    }
+                }
+            });
+
+            if doubled {
+                fire(action, event.source, event.time);
+            }
+        }
+        // Only reachable when the binding changed mid-hold
+        Trigger::Hold(_) => {}
+    }
+}
+
+fn cancel_hold() {
+    let Some(hold) = PENDING_HOLD.with(|pending| pending.borrow_mut().take()) else {
+        return;
+    };
+
+    if !hold.fired {
+        hold.handle.cancel();
+        send_event(Box::new(HoldProgressEvent::new(None, hold.duration)));
+    }
+}
+
+fn fire(action: Action, source: InputSource, time: Instant) {
+    if action == Action::ToggleLock {
+        set_lock(if is_locked() { LockState::Unlocked } else { LockState::Locked });
+        return;
+    }
+
+    send_event(Box::new(ActionEvent::new(action, source, time)));
+}
+
+fn is_locked() -> bool {
+    LOCK.get() != LockState::Unlocked
+}
+
+fn set_lock(state: LockState) {
+    let was_locked = is_locked();
+    LOCK.set(state);
+
+    if was_locked != is_locked() {
+        if is_locked() {
+            cancel_hold();
+        }
+
+        send_event(Box::new(LockEvent::new(is_locked())));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use std::sync::{Arc, MutexGuard};
+    use super::*;
+    use crate::bus::event_bus::tests::test_lock;
+    use crate::bus::events::button_event::ButtonState;
+    use crate::input::bindings::Bindings;
+    use crate::input::chord::Modifiers;
+    use crate::input::trigger::DOUBLE_TAP_WINDOW;
+    use crate::settings::{self, Settings};
+    use crate::utils::clock::{self, FakeClock};
+
+    struct Harness {
+        clock: Arc<FakeClock>,
+        fired: Rc<RefCell<Vec<(Action, Instant)>>>,
+        _subscriptions: Vec<Subscription>,
+        _serial: MutexGuard<'static, ()>
+    }
+
+    impl Harness {
+
+        fn new(bindings: &[(&str, Action)]) -> Self {
+            let serial = test_lock();
+
+            let mut table = Bindings::defaults();
+            for (text, action) in bindings {
+                table.set(*action, &[Binding::parse(text).unwrap()]);
+            }
+            settings::replace(Settings { bindings: table, ..Settings::default() });
+
+            let clock = Arc::new(FakeClock::default());
+            clock::set_clock(clock.clone());
+
+            let fired = Rc::new(RefCell::new(Vec::new()));
+            let mut subscriptions = init();
+            subscriptions.push(register_event("action_event", {
+                let fired = Rc::clone(&fired);
+                move |_, event| {
+                    let event = event.as_any().downcast_ref::<ActionEvent>().unwrap();
+                    fired.borrow_mut().push((event.action, event.time));
+                    Continue
+                }
+            }, false));
+
+            Self {
+                clock,
+                fired,
+                _subscriptions: subscriptions,
+                _serial: serial
+            }
+        }
+
+        fn press(&self, key: Key) -> Instant {
+            self.send(key, Modifiers::NONE, ButtonState::Pressed)
+        }
+
+        fn release(&self, key: Key) -> Instant {
+            self.send(key, Modifiers::NONE, ButtonState::Released)
+        }
+
+        // The modifiers are the other keys held at the time, as the listener's key state reports them
+        fn send(&self, key: Key, modifiers: Modifiers, state: ButtonState) -> Instant {
+            let time = clock::now();
+            send_event(Box::new(ButtonEvent::new(Input::Key(key), modifiers, state, InputSource::Keyboard, time)));
+            time
+        }
+
+        fn advance(&self, ms: u64) {
+            self.clock.advance(Duration::from_millis(ms));
+        }
+
+        fn fired(&self) -> Vec<(Action, Instant)> {
+            self.fired.borrow().clone()
+        }
+    }
+
+    impl Drop for Harness {
+
+        fn drop(&mut self) {
+            clock::reset_clock();
+        }
+    }
+
+    #[test]
+    fn tap_fires_on_release() {
+        let harness = Harness::new(&[]);
+
+        harness.press(Key::Num1);
+        assert!(harness.fired().is_empty());
+
+        harness.advance(50);
+        let released = harness.release(Key::Num1);
+        assert_eq!(harness.fired(), vec![(Action::SmudgeStart, released)]);
+    }
+
+    #[test]
+    fn double_tap_fires_inside_the_window() {
+        let harness = Harness::new(&[("KeyD@double", Action::SmudgeStart)]);
+
+        harness.release(Key::KeyD);
+        assert!(harness.fired().is_empty());
+
+        harness.advance(DOUBLE_TAP_WINDOW.as_millis() as u64);
+        let second = harness.release(Key::KeyD);
+        assert_eq!(harness.fired(), vec![(Action::SmudgeStart, second)]);
+
+        // The pair is used up, a third tap starts over
+        harness.advance(100);
+        harness.release(Key::KeyD);
+        assert_eq!(harness.fired().len(), 1);
+    }
+
+    #[test]
+    fn double_tap_outside_the_window_starts_over() {
+        let harness = Harness::new(&[("KeyD@double", Action::SmudgeStart)]);
+
+        harness.release(Key::KeyD);
+        harness.advance(DOUBLE_TAP_WINDOW.as_millis() as u64 + 1);
+        harness.release(Key::KeyD);
+        assert!(harness.fired().is_empty());
+
+        harness.advance(200);
+        let third = harness.release(Key::KeyD);
+        assert_eq!(harness.fired(), vec![(Action::SmudgeStart, third)]);
+    }
+
+    #[test]
+    fn hold_fires_once_at_the_end_of_the_hold() {
+        let harness = Harness::new(&[("KeyB@hold800", Action::ResetAll)]);
+
+        let pressed = harness.press(Key::KeyB);
+        harness.advance(800);
+
+        // What the scheduled timer delivers when the hold completes
+        send_event(Box::new(HoldEvent::new(Action::ResetAll, InputSource::Keyboard, pressed)));
+        assert_eq!(harness.fired(), vec![(Action::ResetAll, pressed + Duration::from_millis(800))]);
+
+        // Auto-repeat and the release don't fire it again
+        harness.press(Key::KeyB);
+        harness.release(Key::KeyB);
+        send_event(Box::new(HoldEvent::new(Action::ResetAll, InputSource::Keyboard, pressed)));
+        assert_eq!(harness.fired().len(), 1);
+    }
+
+    #[test]
+    fn hold_released_early_is_cancelled() {
+        let harness = Harness::new(&[("KeyB@hold800", Action::ResetAll)]);
+
+        let pressed = harness.press(Key::KeyB);
+        harness.advance(500);
+        harness.release(Key::KeyB);
+
+        // A timer that was already due when the release arrived
+        send_event(Box::new(HoldEvent::new(Action::ResetAll, InputSource::Keyboard, pressed)));
+        assert!(harness.fired().is_empty());
+    }
+
+    #[test]
+    fn chord_fires_when_the_modifier_comes_up_first() {
+        let harness = Harness::new(&[("Ctrl+Num1", Action::ObamboStart)]);
+
+        harness.send(Key::ControlLeft, Modifiers::NONE, ButtonState::Pressed);
+        harness.send(Key::Num1, Modifiers::CTRL, ButtonState::Pressed);
+        harness.send(Key::ControlLeft, Modifiers::NONE, ButtonState::Released);
+        let released = harness.send(Key::Num1, Modifiers::NONE, ButtonState::Released);
+
+        assert_eq!(harness.fired(), vec![(Action::ObamboStart, released)]);
+    }
+
+    #[test]
+    fn chord_fires_when_the_key_comes_up_first() {
+        let harness = Harness::new(&[("Ctrl+Num1", Action::ObamboStart)]);
+
+        harness.send(Key::ControlLeft, Modifiers::NONE, ButtonState::Pressed);
+        harness.send(Key::Num1, Modifiers::CTRL, ButtonState::Pressed);
+        let released = harness.send(Key::Num1, Modifiers::CTRL, ButtonState::Released);
+        harness.send(Key::ControlLeft, Modifiers::NONE, ButtonState::Released);
+
+        assert_eq!(harness.fired(), vec![(Action::ObamboStart, released)]);
+    }
+
+    #[test]
+    fn extra_modifiers_match_no_chord() {
+        let harness = Harness::new(&[("Ctrl+Num1", Action::ObamboStart)]);
+
+        let ctrl_shift = {
+            let mut modifiers = Modifiers::CTRL;
+            modifiers.insert(Modifiers::SHIFT);
+            modifiers
+        };
+        harness.send(Key::Num1, ctrl_shift, ButtonState::Pressed);
+        harness.send(Key::Num1, ctrl_shift, ButtonState::Released);
+
+        // Neither Ctrl+Num1 nor the bare Num1 of the defaults
+        assert!(harness.fired().is_empty());
+    }
+}
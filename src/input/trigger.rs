@@ -0,0 +1,34 @@
+use std::time::Duration;
+
+// The default, double_tap_ms under [timers] changes it
+pub const DOUBLE_TAP_WINDOW: Duration = Duration::from_millis(400);
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub enum Trigger {
+    #[default]
+    Tap,
+    DoubleTap,
+    Hold(Duration)
+}
+
+impl Trigger {
+
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.trim() {
+            "tap" => Some(Trigger::Tap),
+            "double" => Some(Trigger::DoubleTap),
+            text => {
+                let ms: u64 = text.strip_prefix("hold")?.parse().ok()?;
+                Some(Trigger::Hold(Duration::from_millis(ms)))
+            }
+        }
+    }
+
+    pub fn suffix(&self) -> Option<String> {
+        match self {
+            Trigger::Tap => None,
+            Trigger::DoubleTap => Some(String::from("double")),
+            Trigger::Hold(duration) => Some(format!("hold{}", duration.as_millis()))
+        }
+    }
+}
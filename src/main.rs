@@ -4,18 +4,10 @@ mod gtk4;
 mod bus;
 mod utils;
 mod settings;
+mod input;
 
-use std::path::PathBuf;
-use std::process::{exit, Command};
-use std::{env, thread};
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
-use rdev::{listen, EventType, Key};
-use crate::bus::event_bus::send_event;
-use crate::bus::events::button_event::ButtonEvent;
-use crate::bus::events::inter::event::Event;
-use crate::bus::events::timer_event::TimerEvent;
 use crate::gtk4::app::App;
-use crate::settings::load_settings;
+use crate::input::{listener, router};
 
 //export GTK_DEBUG=interactive
 //$env:GTK_DEBUG="interactive"
@@ -36,36 +28,16 @@ Start-Process -FilePath .\target\release\phastimer.exe
 */
 
 fn main() {
-    unsafe { load_settings(); }
+    settings::load();
+    settings::watcher::watch();
 
-    thread::spawn(|| {
-        if let Err(err) = listen(|event| {
-            match event.event_type {
-                EventType::KeyRelease(key) => {
-                    if key == Key::BackSlash {
-                        exit(0);
-                    }
-                    send_event(Box::new(ButtonEvent::new(key)))
-                }
-                _ => {}
-            }
-        }) {
-            eprintln!("Error: {:?}", err);
-        }
-    });
+    for subscription in router::init() {
+        subscription.detach();
+    }
 
-    thread::spawn(|| {
-        loop {
-            thread::sleep(Duration::from_millis(10));
-            let now = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_millis();
-
-            send_event(Box::new(TimerEvent::new(now)));
-        }
-    });
+    listener::start(settings::input_backend());
 
     let app = App::new();
     app.run();
 }
+
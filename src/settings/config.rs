@@ -0,0 +1,348 @@
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use std::fmt;
+use crate::input::action::Action;
+use crate::input::bindings::Binding;
+use crate::input::chord::Chord;
+use crate::input::layout::Layout;
+use crate::input::listener::InputBackend;
+use crate::settings::Settings;
+use crate::settings::toml::{self, Entry, Value};
+
+// Bump when a key moves or changes meaning, and upgrade older files in parse()
+pub const VERSION: i64 = 2;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    pub line: usize,
+    // Empty when the line couldn't be read far enough to have one
+    pub key: String,
+    pub reason: String
+}
+
+impl Problem {
+
+    pub fn new(line: usize, key: &str, reason: String) -> Self {
+        Self {
+            line,
+            key: key.to_string(),
+            reason
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.key.is_empty() {
+            write!(f, "line {}: {}", self.line, self.reason)
+        } else {
+            write!(f, "line {}: {}: {}", self.line, self.key, self.reason)
+        }
+    }
+}
+
+// Anything wrong falls back to the default for that setting and is reported, never fatal
+pub fn parse(text: &str) -> (Settings, Vec<Problem>) {
+    let (mut entries, errors) = toml::parse(text);
+    let mut problems: Vec<Problem> = errors.into_iter()
+        .map(|error| Problem::new(error.line, "", error.reason))
+        .collect();
+
+    upgrade(&mut entries);
+
+    let mut settings = Settings::default();
+
+    // Defaults the file doesn't replace keep their chords, so its bindings are checked against them too
+    let replaced: HashSet<Action> = entries.iter()
+        .filter(|entry| entry.section == "bindings" && entry.value.as_array().is_some())
+        .filter_map(|entry| Action::from_name(&entry.key))
+        .collect();
+    let mut bound: HashMap<Chord, Action> = Action::ALL.into_iter()
+        .filter(|action| !replaced.contains(action))
+        .flat_map(|action| settings.bindings.bindings_for(action).into_iter().map(move |binding| (binding.chord, action)))
+        .collect();
+
+    for entry in &entries {
+        let key = qualified_key(entry);
+        if entry.section == "bindings" {
+            apply_bindings(&mut settings, entry, &key, &mut bound, &mut problems);
+        } else if let Err(reason) = apply(&mut settings, entry) {
+            problems.push(Problem::new(entry.line, &key, reason));
+        }
+    }
+
+    problems.sort_by_key(|problem| problem.line);
+    (settings, problems)
+}
+
+// Moves keys from older versions to where they live now, one step per version
+fn upgrade(entries: &mut [Entry]) {
+    let version = entries.iter()
+        .find(|entry| entry.section.is_empty() && entry.key == "version")
+        .and_then(|entry| entry.value.as_integer())
+        .unwrap_or(VERSION);
+
+    // 1 kept the game's modifiers with the timers
+    if version < 2 {
+        for entry in entries.iter_mut() {
+            if entry.section == "timers" && (entry.key == "ghost_speed" || entry.key == "blood_moon") {
+                entry.section = String::from("modifiers");
+            }
+        }
+    }
+}
+
+fn qualified_key(entry: &Entry) -> String {
+    if entry.section.is_empty() {
+        entry.key.clone()
+    } else {
+        format!("{}.{}", entry.section, entry.key)
+    }
+}
+
+fn apply(settings: &mut Settings, entry: &Entry) -> Result<(), String> {
+    let value = &entry.value;
+
+    match (entry.section.as_str(), entry.key.as_str()) {
+        ("", "version") => {
+            let version = integer(value)?;
+            if version > VERSION {
+                return Err(format!("{} is newer than this build understands ({}), settings it doesn't know are ignored", version, VERSION));
+            }
+        }
+        ("modifiers", "ghost_speed") => settings.ghost_speed = ranged(value, 0, 4)?,
+        ("modifiers", "blood_moon") => settings.blood_moon = boolean(value)?,
+        ("timers", "double_tap_ms") => settings.double_tap_window = Duration::from_millis(ranged(value, 100, 1000)? as u64),
+        ("display", "location") => settings.location = ranged(value, 0, 2)?,
+        ("input", "backend") => {
+            let name = string(value)?;
+            settings.input_backend = InputBackend::from_name(name)
+                .ok_or_else(|| format!("unknown input backend '{}', expected one of {}", name, InputBackend::ALL.iter().map(|backend| backend.name()).collect::<Vec<_>>().join(", ")))?;
+        }
+        ("input", "keyboard_layout") => {
+            let name = string(value)?;
+            settings.keyboard_layout = match name {
+                "auto" => None,
+                _ => Some(Layout::from_name(name).ok_or_else(|| format!("unknown keyboard layout '{}', expected auto or one of {}", name, Layout::ALL.map(|layout| layout.name()).join(", ")))?)
+            };
+        }
+        ("input", "auto_lock") => settings.auto_lock = boolean(value)?,
+        _ => return Err(String::from("unknown setting"))
+    }
+
+    Ok(())
+}
+
+fn apply_bindings(settings: &mut Settings, entry: &Entry, key: &str, bound: &mut HashMap<Chord, Action>, problems: &mut Vec<Problem>) {
+    let mut report = |reason: String| problems.push(Problem::new(entry.line, key, reason));
+
+    let Some(action) = Action::from_name(&entry.key) else {
+        report(String::from("unknown action"));
+        return;
+    };
+
+    let Some(values) = entry.value.as_array() else {
+        report(String::from("expected a list of bindings, like [\"Num1\"]"));
+        return;
+    };
+
+    // A repeated line replaces the earlier one, so its chords are free again
+    bound.retain(|_, a| *a != action);
+
+    let mut parsed = Vec::new();
+    for value in values {
+        let Some(text) = value.as_str() else {
+            report(format!("expected a binding in quotes, found {}", value));
+            continue;
+        };
+
+        let Some(binding) = Binding::parse(text) else {
+            report(format!("'{}' isn't a key, mouse button or trigger this build knows, ignored", text));
+            continue;
+        };
+
+        if parsed.iter().any(|b: &Binding| b.chord == binding.chord) {
+            report(format!("'{}' is listed twice", binding.chord));
+            continue;
+        }
+
+        if let Some(other) = bound.get(&binding.chord) {
+            report(format!("'{}' is already bound to {}, ignored", binding.chord, other.name()));
+            continue;
+        }
+
+        bound.insert(binding.chord, action);
+        parsed.push(binding);
+    }
+
+    // Nothing usable left is a mistake rather than a wish to unbind
+    if parsed.is_empty() && !values.is_empty() {
+        report(String::from("no usable bindings, keeping the default"));
+        keep_default(settings, action, bound);
+        return;
+    }
+
+    // The console is the only way back in, so it can't be unbound
+    if parsed.is_empty() && action == Action::OpenConsole {
+        report(String::from("can't be unbound, keeping the default"));
+        keep_default(settings, action, bound);
+        return;
+    }
+
+    settings.bindings.set(action, &parsed);
+}
+
+// A kept default still holds its chords against the lines after it
+fn keep_default(settings: &Settings, action: Action, bound: &mut HashMap<Chord, Action>) {
+    for binding in settings.bindings.bindings_for(action) {
+        bound.entry(binding.chord).or_insert(action);
+    }
+}
+
+fn integer(value: &Value) -> Result<i64, String> {
+    value.as_integer().ok_or_else(|| format!("expected a whole number, found {}", value))
+}
+
+fn ranged(value: &Value, min: i64, max: i64) -> Result<usize, String> {
+    let number = integer(value)?;
+    if !(min..=max).contains(&number) {
+        return Err(format!("{} is out of range, expected {} to {}", number, min, max));
+    }
+
+    Ok(number as usize)
+}
+
+fn boolean(value: &Value) -> Result<bool, String> {
+    value.as_bool().ok_or_else(|| format!("expected true or false, found {}", value))
+}
+
+fn string(value: &Value) -> Result<&str, String> {
+    value.as_str().ok_or_else(|| format!("expected text in quotes, found {}", value))
+}
+
+const HEADER: &str = "\
+# Phastimer settings, in TOML. The app rewrites this file when they change, so comments added here are lost.
+";
+
+pub fn serialize(settings: &Settings) -> String {
+    let mut contents = format!("{}\nversion = {}\n", HEADER, VERSION);
+
+    contents.push_str("\n[modifiers]\n");
+    contents.push_str(&format!("ghost_speed = {}\n", settings.ghost_speed));
+    contents.push_str(&format!("blood_moon = {}\n", settings.blood_moon));
+
+    contents.push_str("\n[timers]\n");
+    contents.push_str(&format!("double_tap_ms = {}\n", settings.double_tap_window.as_millis()));
+
+    contents.push_str("\n[display]\n");
+    contents.push_str(&format!("location = {}\n", settings.location));
+
+    contents.push_str("\n[input]\n");
+    contents.push_str(&format!("backend = {}\n", Value::String(settings.input_backend.name().to_string())));
+    contents.push_str(&format!("keyboard_layout = {}\n", Value::String(settings.keyboard_layout.map(|layout| layout.name()).unwrap_or("auto").to_string())));
+    contents.push_str(&format!("auto_lock = {}\n", settings.auto_lock));
+
+    contents.push_str("\n[bindings]\n");
+    for action in Action::ALL {
+        let bindings = settings.bindings.bindings_for(action).iter()
+            .map(|binding| Value::String(binding.to_string()))
+            .collect();
+
+        contents.push_str(&format!("{} = {}\n", action.name(), Value::Array(bindings)));
+    }
+
+    contents
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_round_trip() {
+        let (settings, problems) = parse(&serialize(&Settings::default()));
+        assert!(problems.is_empty(), "{:?}", problems);
+        assert_eq!(settings, Settings::default());
+    }
+
+    // evdev only exists on Linux
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn edited_file_round_trips() {
+        let text = "\
+version = 2
+
+[modifiers]
+ghost_speed = 4
+blood_moon = true
+
+[timers]
+double_tap_ms = 250
+
+[display]
+location = 1
+
+[input]
+backend = \"evdev\"
+keyboard_layout = \"azerty\"
+auto_lock = true
+
+[bindings]
+smudge_start = [\"KeyD@double\", \"F1\"]
+reset_all = [\"KeyB@hold800\"]
+quit = []
+";
+
+        let (loaded, problems) = parse(text);
+        assert!(problems.is_empty(), "{:?}", problems);
+        assert_eq!(loaded.ghost_speed, 4);
+        assert!(loaded.blood_moon);
+        assert_eq!(loaded.double_tap_window, Duration::from_millis(250));
+        assert_eq!(loaded.location, 1);
+        assert_eq!(loaded.input_backend, InputBackend::Evdev);
+        assert_eq!(loaded.keyboard_layout, Some(Layout::Azerty));
+        assert!(loaded.auto_lock);
+        assert_eq!(loaded.bindings.bindings_for(Action::SmudgeStart).len(), 2);
+        assert_eq!(loaded.bindings.bindings_for(Action::ResetAll)[0].trigger, crate::input::trigger::Trigger::Hold(std::time::Duration::from_millis(800)));
+        assert!(loaded.bindings.bindings_for(Action::Quit).is_empty());
+
+        let saved = serialize(&loaded);
+        let (reloaded, problems) = parse(&saved);
+        assert!(problems.is_empty(), "{:?}", problems);
+        assert_eq!(reloaded, loaded);
+        assert_eq!(serialize(&reloaded), saved);
+    }
+
+    #[test]
+    fn chords_the_defaults_still_hold_are_taken() {
+        let (settings, problems) = parse("[bindings]\ntap = [\"Num2\", \"Num9\"]\n");
+        assert_eq!(settings.bindings.bindings_for(Action::Tap), [Binding::parse("Num9").unwrap()]);
+        assert_eq!(settings.bindings.bindings_for(Action::SmudgeReset), Settings::default().bindings.bindings_for(Action::SmudgeReset));
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].reason.contains("smudge_reset"), "{}", problems[0]);
+
+        // Once smudge_reset moves, its old chord is free, wherever the line is
+        let (settings, problems) = parse("[bindings]\ntap = [\"Num2\"]\nsmudge_reset = [\"Num9\"]\n");
+        assert!(problems.is_empty(), "{:?}", problems);
+        assert_eq!(settings.bindings.bindings_for(Action::Tap), [Binding::parse("Num2").unwrap()]);
+    }
+
+    #[test]
+    fn out_of_range_values_fall_back_to_the_default() {
+        let (settings, problems) = parse("[modifiers]\nghost_speed = 9\nblood_moon = 1\n[timers]\ndouble_tap_ms = 50\n");
+        assert_eq!(settings, Settings::default());
+        assert_eq!(problems.iter().map(|problem| problem.key.as_str()).collect::<Vec<_>>(), ["modifiers.ghost_speed", "modifiers.blood_moon", "timers.double_tap_ms"]);
+    }
+
+    #[test]
+    fn version_one_modifiers_move_out_of_timers() {
+        let (settings, problems) = parse("version = 1\n[timers]\nghost_speed = 3\nblood_moon = true\n");
+        assert!(problems.is_empty(), "{:?}", problems);
+        assert_eq!((settings.ghost_speed, settings.blood_moon), (3, true));
+
+        // Only files from before the move, in a current one they're just in the wrong place
+        let (_, problems) = parse("version = 2\n[timers]\nghost_speed = 3\n");
+        assert_eq!(problems.iter().map(|problem| problem.key.as_str()).collect::<Vec<_>>(), ["timers.ghost_speed"]);
+    }
+}
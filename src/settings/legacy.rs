@@ -0,0 +1,78 @@
+use crate::input::action::Action;
+use crate::input::bindings::parse_bindings;
+use crate::input::layout::Layout;
+use crate::input::listener::InputBackend;
+use crate::settings::Settings;
+use crate::settings::config::Problem;
+
+// The key=value config.ini used before config.toml, only read to migrate it
+const BINDING_KEYS: [(&str, Action); 9] = [
+    ("key_timer_start", Action::SmudgeStart),
+    ("key_timer_reset", Action::SmudgeReset),
+    ("key_obambo_start", Action::ObamboStart),
+    ("key_obambo_reset", Action::ObamboReset),
+    ("key_ms", Action::Tap),
+    ("key_reset", Action::ResetAll),
+    ("key_lock", Action::ToggleLock),
+    ("key_console", Action::OpenConsole),
+    ("key_quit", Action::Quit)
+];
+
+pub fn parse(text: &str) -> (Settings, Vec<Problem>) {
+    let mut settings = Settings::default();
+    let mut problems = Vec::new();
+
+    for (i, line) in text.lines().enumerate() {
+        let mut report = |key: &str, reason: String| problems.push(Problem::new(i + 1, key, reason));
+
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        let Some((k, v)) = line.split_once('=') else {
+            report("", format!("expected 'key=value', found '{}'", line));
+            continue;
+        };
+        let k = k.trim();
+        let v = v.trim();
+
+        if let Some((_, action)) = BINDING_KEYS.iter().find(|(name, _)| *name == k) {
+            match parse_bindings(v) {
+                // The console is the only way back in, so it can't be unbound
+                Some(parsed) if parsed.is_empty() && *action == Action::OpenConsole => report(k, String::from("can't be unbound, keeping the default")),
+                Some(parsed) => settings.bindings.set(*action, &parsed),
+                None => report(k, format!("'{}' isn't a key, mouse button or trigger this build knows, keeping the default", v))
+            }
+            continue;
+        }
+
+        let valid = match k {
+            "ghost_speed" => set(&mut settings.ghost_speed, v.parse().ok().filter(|speed| *speed <= 4)),
+            "blood_moon" => set(&mut settings.blood_moon, v.parse().ok()),
+            "location" => set(&mut settings.location, v.parse().ok().filter(|location| *location <= 2)),
+            "auto_lock" => set(&mut settings.auto_lock, v.parse().ok()),
+            "keyboard_layout" => set(&mut settings.keyboard_layout, if v == "auto" { Some(None) } else { Layout::from_name(v).map(Some) }),
+            "input_backend" => set(&mut settings.input_backend, InputBackend::from_name(v)),
+            _ => {
+                report(k, String::from("unknown setting"));
+                continue;
+            }
+        };
+
+        if !valid {
+            report(k, format!("invalid value '{}', keeping the default", v));
+        }
+    }
+
+    (settings, problems)
+}
+
+fn set<T>(field: &mut T, value: Option<T>) -> bool {
+    let Some(value) = value else {
+        return false;
+    };
+
+    *field = value;
+    true
+}
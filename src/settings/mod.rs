@@ -0,0 +1,311 @@
+pub mod config;
+pub mod legacy;
+pub mod storage;
+pub mod toml;
+pub mod watcher;
+
+use std::{fs, io};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use rdev::Key;
+use crate::bus::event_bus::send_event;
+use crate::bus::events::config_problems_event::ConfigProblemsEvent;
+use crate::bus::events::save_result_event::SaveResultEvent;
+use crate::bus::events::settings_event::SettingsEvent;
+use crate::input::action::Action;
+use crate::input::bindings::Bindings;
+use crate::input::chord::Chord;
+use crate::input::inputs::{Input, MouseButton};
+use crate::input::layout::Layout;
+use crate::input::listener::InputBackend;
+use crate::input::trigger::DOUBLE_TAP_WINDOW;
+use crate::settings::config::Problem;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    pub ghost_speed: usize,
+    pub blood_moon: bool,
+    // How soon a second press has to follow for @double
+    pub double_tap_window: Duration,
+    pub location: usize,
+    pub auto_lock: bool,
+    // None follows the layout of the focused window
+    pub keyboard_layout: Option<Layout>,
+    // Only read at startup
+    pub input_backend: InputBackend,
+    pub bindings: Bindings
+}
+
+impl Settings {
+
+    const fn new() -> Self {
+        Self {
+            ghost_speed: 2,
+            blood_moon: false,
+            double_tap_window: DOUBLE_TAP_WINDOW,
+            location: 0,
+            auto_lock: false,
+            keyboard_layout: None,
+            input_backend: InputBackend::Rdev,
+            bindings: Bindings::new()
+        }
+    }
+}
+
+impl Default for Settings {
+
+    fn default() -> Self {
+        Self {
+            bindings: Bindings::defaults(),
+            ..Self::new()
+        }
+    }
+}
+
+static SETTINGS: RwLock<Settings> = RwLock::new(Settings::new());
+
+// Whether this run already put the file it started with into the backups
+static BACKED_UP: AtomicBool = AtomicBool::new(false);
+
+pub fn load() {
+    let settings = match fs::read_to_string(config_path()) {
+        Ok(text) => {
+            let (settings, problems) = config::parse(&text);
+            report_problems(&config_path(), problems, false);
+            settings
+        }
+        Err(_) => migrate_legacy().unwrap_or_default()
+    };
+
+    *SETTINGS.write().unwrap() = settings;
+    publish();
+}
+
+// Upgrades a config.ini from before config.toml, which is left in place
+fn migrate_legacy() -> Option<Settings> {
+    let text = fs::read_to_string(legacy_config_path()).ok()?;
+    let (settings, problems) = legacy::parse(&text);
+    report_problems(&legacy_config_path(), problems, false);
+
+    if let Err(err) = write(&settings) {
+        eprintln!("Error migrating config.ini: {}", err);
+    }
+
+    Some(settings)
+}
+
+// Picks up hand edits to the file, all or nothing so a half-made edit never applies
+pub fn reload() {
+    let path = config_path();
+    let Ok(text) = fs::read_to_string(&path) else {
+        return;
+    };
+
+    let (settings, problems) = config::parse(&text);
+    if !problems.is_empty() {
+        report_problems(&path, problems, true);
+        eprintln!("Ignored the edit to {} until the problems are fixed", path.display());
+        return;
+    }
+
+    report_problems(&path, problems, false);
+
+    // Already on disk, so it isn't saved again
+    let changed = {
+        let mut current = SETTINGS.write().unwrap();
+        let changed = *current != settings;
+        *current = settings;
+        changed
+    };
+
+    if changed {
+        publish();
+    }
+}
+
+fn report_problems(path: &Path, problems: Vec<Problem>, rejected: bool) {
+    for problem in &problems {
+        eprintln!("Problem in {}, {}", path.display(), problem);
+    }
+
+    // Sticky, so the console shows them whenever it's opened
+    send_event(Box::new(ConfigProblemsEvent::new(problems, rejected)));
+}
+
+pub fn save() {
+    let result = write(&current());
+    if let Err(err) = &result {
+        eprintln!("Error saving settings: {}", err);
+    }
+
+    // Sticky, so a failure is still shown when the console is opened later
+    send_event(Box::new(SaveResultEvent::new(result.err().map(|err| err.to_string()))));
+}
+
+fn write(settings: &Settings) -> io::Result<()> {
+    let path = config_path();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    // Sliders save on every step, so only the first save of a run makes a backup
+    if !BACKED_UP.load(Ordering::Relaxed) {
+        storage::rotate_backups(&path)?;
+        BACKED_UP.store(true, Ordering::Relaxed);
+    }
+
+    storage::write_atomic(&path, config::serialize(settings))
+}
+
+pub fn restore_previous() -> io::Result<()> {
+    let path = config_path();
+    let newest = storage::backup_path(&path, 1);
+    let text = storage::pop_backup(&path)?;
+
+    let (settings, problems) = config::parse(&text);
+    report_problems(&newest, problems, false);
+
+    // Going back shouldn't push what's being undone into the backups
+    BACKED_UP.store(true, Ordering::Relaxed);
+    update(|current| *current = settings);
+    Ok(())
+}
+
+pub fn reset_to_defaults() {
+    // Backs up what's there first, so a reset can be undone with restore_previous
+    BACKED_UP.store(false, Ordering::Relaxed);
+    update(|current| *current = Settings::default());
+}
+
+// Swaps the settings in without publishing or saving, so tests never touch the real config
+#[cfg(test)]
+pub fn replace(settings: Settings) {
+    *SETTINGS.write().unwrap() = settings;
+}
+
+pub fn publish() {
+    send_event(Box::new(SettingsEvent::new(current())));
+}
+
+pub fn current() -> Settings {
+    SETTINGS.read().unwrap().clone()
+}
+
+// Every change goes through here, so listeners hear about it and it's written out once
+pub fn update<F>(f: F)
+where
+    F: FnOnce(&mut Settings),
+{
+    let changed = {
+        let mut settings = SETTINGS.write().unwrap();
+        let before = settings.clone();
+        f(&mut settings);
+        *settings != before
+    };
+
+    if changed {
+        publish();
+        save();
+    }
+}
+
+pub fn ghost_speed() -> usize {
+    SETTINGS.read().unwrap().ghost_speed
+}
+
+pub fn set_ghost_speed(ghost_speed: usize) {
+    update(|settings| settings.ghost_speed = ghost_speed);
+}
+
+pub fn blood_moon() -> bool {
+    SETTINGS.read().unwrap().blood_moon
+}
+
+pub fn set_blood_moon(blood_moon: bool) {
+    update(|settings| settings.blood_moon = blood_moon);
+}
+
+pub fn double_tap_window() -> Duration {
+    SETTINGS.read().unwrap().double_tap_window
+}
+
+pub fn location() -> usize {
+    SETTINGS.read().unwrap().location
+}
+
+pub fn set_location(location: usize) {
+    update(|settings| settings.location = location);
+}
+
+pub fn auto_lock() -> bool {
+    SETTINGS.read().unwrap().auto_lock
+}
+
+pub fn set_auto_lock(auto_lock: bool) {
+    update(|settings| settings.auto_lock = auto_lock);
+}
+
+pub fn keyboard_layout_setting() -> Option<Layout> {
+    SETTINGS.read().unwrap().keyboard_layout
+}
+
+pub fn keyboard_layout() -> Layout {
+    keyboard_layout_setting().unwrap_or_else(Layout::detected)
+}
+
+pub fn set_keyboard_layout(layout: Option<Layout>) {
+    update(|settings| settings.keyboard_layout = layout);
+}
+
+pub fn input_backend() -> InputBackend {
+    SETTINGS.read().unwrap().input_backend
+}
+
+pub fn with_bindings<F, R>(f: F) -> R
+where
+    F: FnOnce(&Bindings) -> R,
+{
+    f(&SETTINGS.read().unwrap().bindings)
+}
+
+pub fn update_bindings<F>(f: F)
+where
+    F: FnOnce(&mut Bindings),
+{
+    update(|settings| f(&mut settings.bindings));
+}
+
+fn config_path() -> PathBuf {
+    config_dir().join("config.toml")
+}
+
+fn legacy_config_path() -> PathBuf {
+    config_dir().join("config.ini")
+}
+
+fn config_dir() -> PathBuf {
+    if let Ok(appdata) = std::env::var("APPDATA") {
+        return PathBuf::from(appdata).join("PhasTimer");
+    }
+
+    if let Ok(home) = std::env::var("HOME") {
+        return PathBuf::from(home).join(".config").join("phastimer");
+    }
+
+    PathBuf::new()
+}
+
+pub fn verify_key_bind(chord: &Chord, action: Action) -> bool {
+    match chord.input {
+        Input::Key(Key::Unknown(0)) => return false,
+        Input::Mouse(MouseButton::Left) if chord.modifiers.is_empty() => return false,
+        _ => {}
+    }
+
+    match with_bindings(|bindings| bindings.conflict(chord)) {
+        Some(bound) => bound == action,
+        None => true
+    }
+}
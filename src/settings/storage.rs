@@ -0,0 +1,106 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+pub const BACKUPS: usize = 3;
+
+// Written next to the target and renamed over it, so a crash leaves either the old file or the new one
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let temp = sibling(path, "tmp");
+
+    let result = File::create(&temp)
+        .and_then(|mut file| {
+            file.write_all(contents.as_ref())?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&temp, path));
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+
+    result
+}
+
+// config.toml.bak1 is the newest
+pub fn backup_path(path: &Path, n: usize) -> PathBuf {
+    sibling(path, &format!("bak{}", n))
+}
+
+pub fn rotate_backups(path: &Path) -> io::Result<()> {
+    let Ok(current) = fs::read(path) else {
+        return Ok(());
+    };
+
+    // Nothing new to keep, and a run of identical backups would make restoring pointless
+    if fs::read(backup_path(path, 1)).is_ok_and(|newest| newest == current) {
+        return Ok(());
+    }
+
+    for n in (1..BACKUPS).rev() {
+        rename_if_exists(&backup_path(path, n), &backup_path(path, n + 1))?;
+    }
+
+    write_atomic(&backup_path(path, 1), current)
+}
+
+// Takes the newest backup out of the rotation, so the next call goes further back
+pub fn pop_backup(path: &Path) -> io::Result<String> {
+    let newest = backup_path(path, 1);
+    let text = fs::read_to_string(&newest)?;
+    fs::remove_file(&newest)?;
+
+    for n in 2..=BACKUPS {
+        rename_if_exists(&backup_path(path, n), &backup_path(path, n - 1))?;
+    }
+
+    Ok(text)
+}
+
+fn rename_if_exists(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result
+    }
+}
+
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("phastimer-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("config.toml")
+    }
+
+    #[test]
+    fn backups_rotate_and_pop_back() {
+        let path = scratch("rotate");
+        for contents in ["one", "two", "two", "three", "four"] {
+            write_atomic(&path, contents).unwrap();
+            rotate_backups(&path).unwrap();
+        }
+
+        let names: Vec<_> = fs::read_dir(path.parent().unwrap()).unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        assert!(names.iter().all(|name| !name.ends_with(".tmp")), "{:?}", names);
+        assert!(!backup_path(&path, BACKUPS + 1).exists());
+
+        assert_eq!(pop_backup(&path).unwrap(), "four");
+        assert_eq!(pop_backup(&path).unwrap(), "three");
+        assert_eq!(pop_backup(&path).unwrap(), "two");
+        assert!(pop_backup(&path).is_err());
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
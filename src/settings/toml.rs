@@ -0,0 +1,160 @@
+use std::borrow::Cow;
+use std::fmt;
+use toml::de::{DeTable, DeValue};
+use toml::Spanned;
+
+pub use toml::Value;
+
+// Full TOML through the toml crate, flattened to one entry per setting with the line it's on,
+// so a problem can point at the line and the rest of the file still counts
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub line: usize,
+    // Empty for keys above the first section header
+    pub section: String,
+    pub key: String,
+    pub value: Value
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxError {
+    pub line: usize,
+    pub reason: String
+}
+
+impl fmt::Display for SyntaxError {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.reason)
+    }
+}
+
+// Every error is reported, and the lines the parser could read still count
+pub fn parse(text: &str) -> (Vec<Entry>, Vec<SyntaxError>) {
+    let (document, errors) = DeTable::parse_recoverable(text);
+
+    let mut entries = Vec::new();
+    for (key, value) in document.get_ref() {
+        match value.get_ref() {
+            // [section] headers, dotted keys and inline tables all land here
+            DeValue::Table(table) => {
+                for (inner, value) in table {
+                    entries.push(entry(text, key.get_ref(), inner, value));
+                }
+            }
+            _ => entries.push(entry(text, "", key, value))
+        }
+    }
+
+    let errors: Vec<SyntaxError> = errors.into_iter()
+        .map(|error| SyntaxError {
+            line: error.span().map_or(1, |span| line_at(text, span.start)),
+            reason: error.message().trim().to_string()
+        })
+        .collect();
+
+    // What the parser guessed for a broken line isn't what was meant, so that setting keeps its default
+    entries.retain(|entry| !errors.iter().any(|error| error.line == entry.line));
+
+    // Tables come back sorted by key, the file's own order reads better
+    entries.sort_by_key(|entry| entry.line);
+
+    (entries, errors)
+}
+
+fn entry(text: &str, section: &str, key: &Spanned<Cow<str>>, value: &Spanned<DeValue>) -> Entry {
+    Entry {
+        line: line_at(text, key.span().start),
+        section: section.to_string(),
+        key: key.get_ref().to_string(),
+        value: to_value(value.get_ref())
+    }
+}
+
+fn line_at(text: &str, offset: usize) -> usize {
+    text.get(..offset).unwrap_or(text).matches('\n').count() + 1
+}
+
+fn to_value(value: &DeValue) -> Value {
+    match value {
+        DeValue::String(value) => Value::String(value.to_string()),
+        // Out of range was already reported by the parser
+        DeValue::Integer(value) => Value::Integer(i64::from_str_radix(value.as_str(), value.radix()).unwrap_or_default()),
+        DeValue::Float(value) => Value::Float(value.as_str().parse().unwrap_or_default()),
+        DeValue::Boolean(value) => Value::Boolean(*value),
+        DeValue::Datetime(value) => Value::Datetime(*value),
+        DeValue::Array(values) => Value::Array(values.iter().map(|value| to_value(value.get_ref())).collect()),
+        DeValue::Table(table) => Value::Table(table.iter().map(|(key, value)| (key.get_ref().to_string(), to_value(value.get_ref()))).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(text: &str) -> Vec<(String, Value)> {
+        let (entries, errors) = parse(text);
+        assert!(errors.is_empty(), "{:?}", errors);
+        entries.into_iter().map(|entry| (entry.key, entry.value)).collect()
+    }
+
+    fn strings(values: &[&str]) -> Value {
+        Value::Array(values.iter().map(|value| Value::String(value.to_string())).collect())
+    }
+
+    #[test]
+    fn reads_sections_and_scalars() {
+        let (entries, errors) = parse("version = 1\n\n[timers]\nghost_speed = 1_0\nblood_moon = false\n");
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(entries, vec![
+            Entry { line: 1, section: String::new(), key: String::from("version"), value: Value::Integer(1) },
+            Entry { line: 4, section: String::from("timers"), key: String::from("ghost_speed"), value: Value::Integer(10) },
+            Entry { line: 5, section: String::from("timers"), key: String::from("blood_moon"), value: Value::Boolean(false) }
+        ]);
+    }
+
+    #[test]
+    fn dotted_keys_and_inline_tables_are_sections_too() {
+        let (entries, errors) = parse("display.location = 1\ninput = { auto_lock = true }\n");
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(entries, vec![
+            Entry { line: 1, section: String::from("display"), key: String::from("location"), value: Value::Integer(1) },
+            Entry { line: 2, section: String::from("input"), key: String::from("auto_lock"), value: Value::Boolean(true) }
+        ]);
+    }
+
+    #[test]
+    fn strings_unescape_and_print_back() {
+        let text = r#"name = "a \"quoted\" \\ path\n	with\ttabs # not a comment""#;
+        let expected = Value::String(String::from("a \"quoted\" \\ path\n\twith\ttabs # not a comment"));
+        assert_eq!(values(text), vec![(String::from("name"), expected.clone())]);
+        assert_eq!(values(&format!("name = {}", expected)), vec![(String::from("name"), expected)]);
+        assert_eq!(values("name = 'C:\\raw'"), vec![(String::from("name"), Value::String(String::from("C:\\raw")))]);
+    }
+
+    #[test]
+    fn arrays_over_several_lines() {
+        let text = "\
+[bindings]
+smudge_start = [
+    \"Num1\", # the default
+    # a spare
+    \"F1]\",
+]
+quit = [\"Escape\"]
+";
+        let (entries, errors) = parse(text);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(entries.len(), 2);
+        assert_eq!((entries[0].line, &entries[0].value), (2, &strings(&["Num1", "F1]"])));
+        assert_eq!((entries[1].line, &entries[1].value), (7, &strings(&["Escape"])));
+    }
+
+    #[test]
+    fn errors_name_their_line_and_the_rest_is_kept() {
+        let (entries, errors) = parse("a = 1\nb = yes\n[s]\nc = 3\nc = 4\n");
+        assert_eq!(entries.iter().map(|entry| (entry.section.as_str(), entry.key.as_str(), entry.value.clone())).collect::<Vec<_>>(), [("", "a", Value::Integer(1)), ("s", "c", Value::Integer(3))]);
+        assert_eq!(errors.iter().map(|error| error.line).collect::<Vec<_>>(), [2, 5]);
+    }
+}
@@ -0,0 +1,32 @@
+use std::cell::Cell;
+use std::fs;
+use std::time::{Duration, SystemTime};
+use gtk4::glib;
+use crate::input::layout::Layout;
+use crate::settings::{config_path, keyboard_layout_setting, publish, reload};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// Polling the modified time is enough for a file edited by hand, and behaves the same on every platform
+pub fn watch() {
+    let last_modified = Cell::new(modified());
+
+    glib::timeout_add_local(POLL_INTERVAL, move || {
+        let modified = modified();
+        if modified != last_modified.get() {
+            last_modified.set(modified);
+            reload();
+        }
+
+        // Labels and the portal's shortcuts follow the detected layout, so they hear when it changes
+        if keyboard_layout_setting().is_none() && Layout::refresh() {
+            publish();
+        }
+
+        glib::ControlFlow::Continue
+    });
+}
+
+fn modified() -> Option<SystemTime> {
+    fs::metadata(config_path()).and_then(|metadata| metadata.modified()).ok()
+}
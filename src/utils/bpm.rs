@@ -39,14 +39,10 @@ fn get_bpm_average(avg_taps: &[f64]) -> f64 {
 
 impl TapState {
 
-    pub fn tap_and_compute(&mut self) -> Option<(u32, f64)> {
-        let now = Instant::now();
-
+    pub fn tap_at_and_compute(&mut self, now: Instant) -> Option<(u32, f64)> {
         // JS resets if gap > 5000ms since last tap :contentReference[oaicite:8]{index=8}
-        if let Some(prev) = self.taps.last().copied() {
-            if now.duration_since(prev) > RESET_GAP {
-                self.reset();
-            }
+        if let Some(prev) = self.taps.last().copied() && now.duration_since(prev) > RESET_GAP {
+            self.reset();
         }
 
         self.taps.push(now);
@@ -80,15 +76,8 @@ impl TapState {
         }
 
         // Same “inaccurate-looking” average as JS (divide by 5 no matter what)
-        let mut bpm = get_bpm_average(&avg_taps);
-
         // JS caps bpm at 600 :contentReference[oaicite:13]{index=13}
-        if bpm > 600.0 {
-            bpm = 600.0;
-        }
-        if bpm < 0.0 {
-            bpm = 0.0;
-        }
+        let bpm = get_bpm_average(&avg_taps).clamp(0.0, 600.0);
 
         // JS get_ms_exact:
         // speed_idx = calibrating ? 2 : UI; here we hardcode idx=2 (100%)
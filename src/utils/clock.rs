@@ -0,0 +1,73 @@
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+#[cfg(test)]
+use std::sync::Mutex;
+#[cfg(test)]
+use std::time::Duration;
+
+pub trait Clock: Send + Sync {
+
+    fn now(&self) -> Instant;
+}
+
+pub struct MonotonicClock;
+
+impl Clock for MonotonicClock {
+
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+// Stands still until advanced, so timing tests don't depend on how fast they run
+#[cfg(test)]
+pub struct FakeClock {
+    start: Instant,
+    elapsed: Mutex<Duration>
+}
+
+#[cfg(test)]
+impl Default for FakeClock {
+
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+            elapsed: Mutex::new(Duration::ZERO)
+        }
+    }
+}
+
+#[cfg(test)]
+impl FakeClock {
+
+    pub fn advance(&self, by: Duration) {
+        *self.elapsed.lock().unwrap() += by;
+    }
+}
+
+#[cfg(test)]
+impl Clock for FakeClock {
+
+    fn now(&self) -> Instant {
+        self.start + *self.elapsed.lock().unwrap()
+    }
+}
+
+static CLOCK: RwLock<Option<Arc<dyn Clock>>> = RwLock::new(None);
+
+pub fn now() -> Instant {
+    match CLOCK.read().unwrap().as_ref() {
+        Some(clock) => clock.now(),
+        None => MonotonicClock.now()
+    }
+}
+
+#[cfg(test)]
+pub fn set_clock(clock: Arc<dyn Clock>) {
+    *CLOCK.write().unwrap() = Some(clock);
+}
+
+#[cfg(test)]
+pub fn reset_clock() {
+    *CLOCK.write().unwrap() = None;
+}
@@ -1,183 +1,172 @@
 use rdev::Key;
+use gtk4::gdk;
 
-pub fn gtk4_key_to_key(key: &gtk4::gdk::Key) -> Key {
+// (key, config name, display label, gdk keyvals that produce it)
+pub(crate) const KEYS: [(Key, &str, &str, &[gdk::Key]); 105] = [
+    (Key::Alt, "Alt", "Alt", &[gdk::Key::Alt_L]),
+    (Key::AltGr, "AltGr", "AltGr", &[gdk::Key::Alt_R, gdk::Key::ISO_Level3_Shift]),
+    (Key::Backspace, "Backspace", "Backspace", &[gdk::Key::BackSpace]),
+    (Key::CapsLock, "CapsLock", "Caps Lock", &[gdk::Key::Caps_Lock]),
+    (Key::ControlLeft, "ControlLeft", "Left Ctrl", &[gdk::Key::Control_L]),
+    (Key::ControlRight, "ControlRight", "Right Ctrl", &[gdk::Key::Control_R]),
+    (Key::Delete, "Delete", "Delete", &[gdk::Key::Delete]),
+    (Key::DownArrow, "DownArrow", "Down", &[gdk::Key::Down]),
+    (Key::End, "End", "End", &[gdk::Key::End]),
+    (Key::Escape, "Escape", "Esc", &[gdk::Key::Escape]),
+    (Key::F1, "F1", "F1", &[gdk::Key::F1]),
+    (Key::F2, "F2", "F2", &[gdk::Key::F2]),
+    (Key::F3, "F3", "F3", &[gdk::Key::F3]),
+    (Key::F4, "F4", "F4", &[gdk::Key::F4]),
+    (Key::F5, "F5", "F5", &[gdk::Key::F5]),
+    (Key::F6, "F6", "F6", &[gdk::Key::F6]),
+    (Key::F7, "F7", "F7", &[gdk::Key::F7]),
+    (Key::F8, "F8", "F8", &[gdk::Key::F8]),
+    (Key::F9, "F9", "F9", &[gdk::Key::F9]),
+    (Key::F10, "F10", "F10", &[gdk::Key::F10]),
+    (Key::F11, "F11", "F11", &[gdk::Key::F11]),
+    (Key::F12, "F12", "F12", &[gdk::Key::F12]),
+    (Key::Home, "Home", "Home", &[gdk::Key::Home]),
+    (Key::LeftArrow, "LeftArrow", "Left", &[gdk::Key::Left]),
+    (Key::MetaLeft, "MetaLeft", "Left Win", &[gdk::Key::Meta_L, gdk::Key::Super_L]),
+    (Key::MetaRight, "MetaRight", "Right Win", &[gdk::Key::Meta_R, gdk::Key::Super_R]),
+    (Key::PageDown, "PageDown", "Page Down", &[gdk::Key::Page_Down]),
+    (Key::PageUp, "PageUp", "Page Up", &[gdk::Key::Page_Up]),
+    (Key::Return, "Return", "Enter", &[gdk::Key::Return]),
+    (Key::RightArrow, "RightArrow", "Right", &[gdk::Key::Right]),
+    (Key::ShiftLeft, "ShiftLeft", "Left Shift", &[gdk::Key::Shift_L]),
+    (Key::ShiftRight, "ShiftRight", "Right Shift", &[gdk::Key::Shift_R]),
+    (Key::Space, "Space", "Space", &[gdk::Key::space]),
+    (Key::Tab, "Tab", "Tab", &[gdk::Key::Tab, gdk::Key::ISO_Left_Tab]),
+    (Key::UpArrow, "UpArrow", "Up", &[gdk::Key::Up]),
+    (Key::PrintScreen, "PrintScreen", "Print Screen", &[gdk::Key::Print]),
+    (Key::ScrollLock, "ScrollLock", "Scroll Lock", &[gdk::Key::Scroll_Lock]),
+    (Key::Pause, "Pause", "Pause", &[gdk::Key::Pause]),
+    (Key::NumLock, "NumLock", "Num Lock", &[gdk::Key::Num_Lock]),
+    (Key::BackQuote, "BackQuote", "`", &[gdk::Key::grave, gdk::Key::asciitilde]),
+    (Key::Num1, "Num1", "1", &[gdk::Key::_1, gdk::Key::exclam]),
+    (Key::Num2, "Num2", "2", &[gdk::Key::_2, gdk::Key::at]),
+    (Key::Num3, "Num3", "3", &[gdk::Key::_3, gdk::Key::numbersign]),
+    (Key::Num4, "Num4", "4", &[gdk::Key::_4, gdk::Key::dollar]),
+    (Key::Num5, "Num5", "5", &[gdk::Key::_5, gdk::Key::percent]),
+    (Key::Num6, "Num6", "6", &[gdk::Key::_6, gdk::Key::asciicircum]),
+    (Key::Num7, "Num7", "7", &[gdk::Key::_7, gdk::Key::ampersand]),
+    (Key::Num8, "Num8", "8", &[gdk::Key::_8, gdk::Key::asterisk]),
+    (Key::Num9, "Num9", "9", &[gdk::Key::_9, gdk::Key::parenleft]),
+    (Key::Num0, "Num0", "0", &[gdk::Key::_0, gdk::Key::parenright]),
+    (Key::Minus, "Minus", "-", &[gdk::Key::minus, gdk::Key::underscore]),
+    (Key::Equal, "Equal", "=", &[gdk::Key::equal, gdk::Key::plus]),
+    (Key::KeyQ, "KeyQ", "Q", &[gdk::Key::q, gdk::Key::Q]),
+    (Key::KeyW, "KeyW", "W", &[gdk::Key::w, gdk::Key::W]),
+    (Key::KeyE, "KeyE", "E", &[gdk::Key::e, gdk::Key::E]),
+    (Key::KeyR, "KeyR", "R", &[gdk::Key::r, gdk::Key::R]),
+    (Key::KeyT, "KeyT", "T", &[gdk::Key::t, gdk::Key::T]),
+    (Key::KeyY, "KeyY", "Y", &[gdk::Key::y, gdk::Key::Y]),
+    (Key::KeyU, "KeyU", "U", &[gdk::Key::u, gdk::Key::U]),
+    (Key::KeyI, "KeyI", "I", &[gdk::Key::i, gdk::Key::I]),
+    (Key::KeyO, "KeyO", "O", &[gdk::Key::o, gdk::Key::O]),
+    (Key::KeyP, "KeyP", "P", &[gdk::Key::p, gdk::Key::P]),
+    (Key::LeftBracket, "LeftBracket", "[", &[gdk::Key::bracketleft, gdk::Key::braceleft]),
+    (Key::RightBracket, "RightBracket", "]", &[gdk::Key::bracketright, gdk::Key::braceright]),
+    (Key::KeyA, "KeyA", "A", &[gdk::Key::a, gdk::Key::A]),
+    (Key::KeyS, "KeyS", "S", &[gdk::Key::s, gdk::Key::S]),
+    (Key::KeyD, "KeyD", "D", &[gdk::Key::d, gdk::Key::D]),
+    (Key::KeyF, "KeyF", "F", &[gdk::Key::f, gdk::Key::F]),
+    (Key::KeyG, "KeyG", "G", &[gdk::Key::g, gdk::Key::G]),
+    (Key::KeyH, "KeyH", "H", &[gdk::Key::h, gdk::Key::H]),
+    (Key::KeyJ, "KeyJ", "J", &[gdk::Key::j, gdk::Key::J]),
+    (Key::KeyK, "KeyK", "K", &[gdk::Key::k, gdk::Key::K]),
+    (Key::KeyL, "KeyL", "L", &[gdk::Key::l, gdk::Key::L]),
+    (Key::SemiColon, "SemiColon", ";", &[gdk::Key::semicolon, gdk::Key::colon]),
+    (Key::Quote, "Quote", "'", &[gdk::Key::apostrophe, gdk::Key::quotedbl]),
+    (Key::BackSlash, "BackSlash", "\\", &[gdk::Key::backslash, gdk::Key::bar]),
+    (Key::IntlBackslash, "IntlBackslash", "Intl \\", &[gdk::Key::less, gdk::Key::greater]),
+    (Key::KeyZ, "KeyZ", "Z", &[gdk::Key::z, gdk::Key::Z]),
+    (Key::KeyX, "KeyX", "X", &[gdk::Key::x, gdk::Key::X]),
+    (Key::KeyC, "KeyC", "C", &[gdk::Key::c, gdk::Key::C]),
+    (Key::KeyV, "KeyV", "V", &[gdk::Key::v, gdk::Key::V]),
+    (Key::KeyB, "KeyB", "B", &[gdk::Key::b, gdk::Key::B]),
+    (Key::KeyN, "KeyN", "N", &[gdk::Key::n, gdk::Key::N]),
+    (Key::KeyM, "KeyM", "M", &[gdk::Key::m, gdk::Key::M]),
+    (Key::Comma, "Comma", ",", &[gdk::Key::comma]),
+    (Key::Dot, "Dot", ".", &[gdk::Key::period]),
+    (Key::Slash, "Slash", "/", &[gdk::Key::slash, gdk::Key::question]),
+    (Key::Insert, "Insert", "Insert", &[gdk::Key::Insert]),
+    (Key::KpReturn, "KpReturn", "Numpad Enter", &[gdk::Key::KP_Enter]),
+    (Key::KpMinus, "KpMinus", "Numpad -", &[gdk::Key::KP_Subtract]),
+    (Key::KpPlus, "KpPlus", "Numpad +", &[gdk::Key::KP_Add]),
+    (Key::KpMultiply, "KpMultiply", "Numpad *", &[gdk::Key::KP_Multiply]),
+    (Key::KpDivide, "KpDivide", "Numpad /", &[gdk::Key::KP_Divide]),
+    (Key::Kp0, "Kp0", "Numpad 0", &[gdk::Key::KP_0, gdk::Key::KP_Insert]),
+    (Key::Kp1, "Kp1", "Numpad 1", &[gdk::Key::KP_1, gdk::Key::KP_End]),
+    (Key::Kp2, "Kp2", "Numpad 2", &[gdk::Key::KP_2, gdk::Key::KP_Down]),
+    (Key::Kp3, "Kp3", "Numpad 3", &[gdk::Key::KP_3, gdk::Key::KP_Page_Down]),
+    (Key::Kp4, "Kp4", "Numpad 4", &[gdk::Key::KP_4, gdk::Key::KP_Left]),
+    (Key::Kp5, "Kp5", "Numpad 5", &[gdk::Key::KP_5, gdk::Key::KP_Begin]),
+    (Key::Kp6, "Kp6", "Numpad 6", &[gdk::Key::KP_6, gdk::Key::KP_Right]),
+    (Key::Kp7, "Kp7", "Numpad 7", &[gdk::Key::KP_7, gdk::Key::KP_Home]),
+    (Key::Kp8, "Kp8", "Numpad 8", &[gdk::Key::KP_8, gdk::Key::KP_Up]),
+    (Key::Kp9, "Kp9", "Numpad 9", &[gdk::Key::KP_9, gdk::Key::KP_Page_Up]),
+    (Key::KpDelete, "KpDelete", "Numpad .", &[gdk::Key::KP_Decimal, gdk::Key::KP_Delete, gdk::Key::KP_Separator]),
+    (Key::Function, "Function", "Fn", &[])
+];
+
+// Windows virtual key codes rdev has no variant for
+#[cfg(windows)]
+const EXTRA_LABELS: [(u32, &str); 12] = [
+    (0x7C, "F13"),
+    (0x7D, "F14"),
+    (0x7E, "F15"),
+    (0x7F, "F16"),
+    (0x80, "F17"),
+    (0x81, "F18"),
+    (0x82, "F19"),
+    (0x83, "F20"),
+    (0x84, "F21"),
+    (0x85, "F22"),
+    (0x86, "F23"),
+    (0x87, "F24")
+];
+
+pub fn key_name(key: Key) -> String {
     match key {
-        &gtk4::gdk::Key::Alt_L => Key::Alt,
-        &gtk4::gdk::Key::Alt_R => Key::AltGr,
-        &gtk4::gdk::Key::BackSpace => Key::Backspace,
-        &gtk4::gdk::Key::Caps_Lock => Key::CapsLock,
-        &gtk4::gdk::Key::Control_L => Key::ControlLeft,
-        &gtk4::gdk::Key::Control_R => Key::ControlRight,
-        &gtk4::gdk::Key::Delete => Key::Delete,
-        &gtk4::gdk::Key::Down => Key::DownArrow,
-        &gtk4::gdk::Key::End => Key::End,
-        &gtk4::gdk::Key::Escape => Key::Escape,
-        &gtk4::gdk::Key::F1 => Key::F1,
-        &gtk4::gdk::Key::F10 => Key::F10,
-        &gtk4::gdk::Key::F11 => Key::F11,
-        &gtk4::gdk::Key::F12 => Key::F12,
-        &gtk4::gdk::Key::F2 => Key::F2,
-        &gtk4::gdk::Key::F3 => Key::F3,
-        &gtk4::gdk::Key::F4 => Key::F4,
-        &gtk4::gdk::Key::F5 => Key::F5,
-        &gtk4::gdk::Key::F6 => Key::F6,
-        &gtk4::gdk::Key::F7 => Key::F7,
-        &gtk4::gdk::Key::F8 => Key::F8,
-        &gtk4::gdk::Key::F9 => Key::F9,
-        &gtk4::gdk::Key::Home => Key::Home,
-        &gtk4::gdk::Key::Left => Key::LeftArrow,
-        &gtk4::gdk::Key::Meta_L => Key::MetaLeft,
-        &gtk4::gdk::Key::Meta_R => Key::MetaRight,
-        &gtk4::gdk::Key::Page_Down => Key::PageDown,
-        &gtk4::gdk::Key::Page_Up => Key::PageUp,
-        &gtk4::gdk::Key::Return => Key::Return,
-        &gtk4::gdk::Key::Right => Key::RightArrow,
-        &gtk4::gdk::Key::Shift_L => Key::ShiftLeft,
-        &gtk4::gdk::Key::Shift_R => Key::ShiftRight,
-        &gtk4::gdk::Key::Tab => Key::Tab,
-        &gtk4::gdk::Key::Up => Key::UpArrow,
-        &gtk4::gdk::Key::Print => Key::PrintScreen,
-        &gtk4::gdk::Key::Scroll_Lock => Key::ScrollLock,
-        &gtk4::gdk::Key::Pause => Key::Pause,
-        &gtk4::gdk::Key::Num_Lock => Key::NumLock,
-        &gtk4::gdk::Key::grave => Key::BackQuote,
-        &gtk4::gdk::Key::_1 => Key::Num1,
-        &gtk4::gdk::Key::_2 => Key::Num2,
-        &gtk4::gdk::Key::_3 => Key::Num3,
-        &gtk4::gdk::Key::_4 => Key::Num4,
-        &gtk4::gdk::Key::_5 => Key::Num5,
-        &gtk4::gdk::Key::_6 => Key::Num6,
-        &gtk4::gdk::Key::_7 => Key::Num7,
-        &gtk4::gdk::Key::_8 => Key::Num8,
-        &gtk4::gdk::Key::_9 => Key::Num9,
-        &gtk4::gdk::Key::_0 => Key::Num0,
-        &gtk4::gdk::Key::minus => Key::Minus,
-        &gtk4::gdk::Key::equal => Key::Equal,
-        &gtk4::gdk::Key::q => Key::KeyQ,
-        &gtk4::gdk::Key::w => Key::KeyW,
-        &gtk4::gdk::Key::e => Key::KeyE,
-        &gtk4::gdk::Key::r => Key::KeyR,
-        &gtk4::gdk::Key::t => Key::KeyT,
-        &gtk4::gdk::Key::y => Key::KeyY,
-        &gtk4::gdk::Key::u => Key::KeyU,
-        &gtk4::gdk::Key::i => Key::KeyI,
-        &gtk4::gdk::Key::o => Key::KeyO,
-        &gtk4::gdk::Key::p => Key::KeyP,
-        &gtk4::gdk::Key::bracketleft => Key::LeftBracket,
-        &gtk4::gdk::Key::bracketright => Key::RightBracket,
-        &gtk4::gdk::Key::a => Key::KeyA,
-        &gtk4::gdk::Key::s => Key::KeyS,
-        &gtk4::gdk::Key::d => Key::KeyD,
-        &gtk4::gdk::Key::f => Key::KeyF,
-        &gtk4::gdk::Key::g => Key::KeyG,
-        &gtk4::gdk::Key::h => Key::KeyH,
-        &gtk4::gdk::Key::j => Key::KeyJ,
-        &gtk4::gdk::Key::k => Key::KeyK,
-        &gtk4::gdk::Key::l => Key::KeyL,
-        &gtk4::gdk::Key::semicolon => Key::SemiColon,
-        &gtk4::gdk::Key::apostrophe => Key::Quote,
-        &gtk4::gdk::Key::backslash => Key::BackSlash,
-        &gtk4::gdk::Key::z => Key::KeyZ,
-        &gtk4::gdk::Key::x => Key::KeyX,
-        &gtk4::gdk::Key::c => Key::KeyC,
-        &gtk4::gdk::Key::v => Key::KeyV,
-        &gtk4::gdk::Key::b => Key::KeyB,
-        &gtk4::gdk::Key::n => Key::KeyN,
-        &gtk4::gdk::Key::m => Key::KeyM,
-        &gtk4::gdk::Key::comma => Key::Comma,
-        &gtk4::gdk::Key::slash => Key::Slash,
-        &gtk4::gdk::Key::Insert => Key::Insert,
-        _ => Key::Unknown(0)
+        Key::Unknown(code) => format!("Unknown({})", code),
+        key => KEYS.iter()
+            .find(|(k, ..)| *k == key)
+            .map(|(_, name, ..)| name.to_string())
+            .unwrap_or_else(|| format!("{:?}", key))
     }
 }
 
-pub fn str_to_key(key: &str) -> Key {
-    match key {
-        "Alt" => Key::Alt,
-        "AltGr" => Key::AltGr,
-        "Backspace" => Key::Backspace,
-        "CapsLock" => Key::CapsLock,
-        "ControlLeft" => Key::ControlLeft,
-        "ControlRight" => Key::ControlRight,
-        "Delete" => Key::Delete,
-        "DownArrow" => Key::DownArrow,
-        "End" => Key::End,
-        "Escape" => Key::Escape,
-        "F1" => Key::F1,
-        "F10" => Key::F10,
-        "F11" => Key::F11,
-        "F12" => Key::F12,
-        "F2" => Key::F2,
-        "F3" => Key::F3,
-        "F4" => Key::F4,
-        "F5" => Key::F5,
-        "F6" => Key::F6,
-        "F7" => Key::F7,
-        "F8" => Key::F8,
-        "F9" => Key::F9,
-        "Home" => Key::Home,
-        "LeftArrow" => Key::LeftArrow,
-        "MetaLeft" => Key::MetaLeft,
-        "MetaRight" => Key::MetaRight,
-        "PageDown" => Key::PageDown,
-        "PageUp" => Key::PageUp,
-        "Return" => Key::Return,
-        "RightArrow" => Key::RightArrow,
-        "ShiftLeft" => Key::ShiftLeft,
-        "ShiftRight" => Key::ShiftRight,
-        "Tab" => Key::Tab,
-        "UpArrow" => Key::UpArrow,
-        "PrintScreen" => Key::PrintScreen,
-        "ScrollLock" => Key::ScrollLock,
-        "Pause" => Key::Pause,
-        "NumLock" => Key::NumLock,
-        "BackQuote" => Key::BackQuote,
-        "Num1" => Key::Num1,
-        "Num2" => Key::Num2,
-        "Num3" => Key::Num3,
-        "Num4" => Key::Num4,
-        "Num5" => Key::Num5,
-        "Num6" => Key::Num6,
-        "Num7" => Key::Num7,
-        "Num8" => Key::Num8,
-        "Num9" => Key::Num9,
-        "Num0" => Key::Num0,
-        "Minus" => Key::Minus,
-        "Equal" => Key::Equal,
-        "KeyQ" => Key::KeyQ,
-        "KeyW" => Key::KeyW,
-        "KeyE" => Key::KeyE,
-        "KeyR" => Key::KeyR,
-        "KeyT" => Key::KeyT,
-        "KeyY" => Key::KeyY,
-        "KeyU" => Key::KeyU,
-        "KeyI" => Key::KeyI,
-        "KeyO" => Key::KeyO,
-        "KeyP" => Key::KeyP,
-        "LeftBracket" => Key::LeftBracket,
-        "RightBracket" => Key::RightBracket,
-        "KeyA" => Key::KeyA,
-        "KeyS" => Key::KeyS,
-        "KeyD" => Key::KeyD,
-        "KeyF" => Key::KeyF,
-        "KeyG" => Key::KeyG,
-        "KeyH" => Key::KeyH,
-        "KeyJ" => Key::KeyJ,
-        "KeyK" => Key::KeyK,
-        "KeyL" => Key::KeyL,
-        "SemiColon" => Key::SemiColon,
-        "Quote" => Key::Quote,
-        "BackSlash" => Key::BackSlash,
-        "KeyZ" => Key::KeyZ,
-        "KeyX" => Key::KeyX,
-        "KeyC" => Key::KeyC,
-        "KeyV" => Key::KeyV,
-        "KeyB" => Key::KeyB,
-        "KeyN" => Key::KeyN,
-        "KeyM" => Key::KeyM,
-        "Comma" => Key::Comma,
-        "Slash" => Key::Slash,
-        "Insert" => Key::Insert,
-        _ => Key::Unknown(0)
+pub fn key_from_name(name: &str) -> Option<Key> {
+    if let Some(code) = name.strip_prefix("Unknown(").and_then(|rest| rest.strip_suffix(')')) {
+        return code.parse().ok().map(Key::Unknown);
     }
+
+    KEYS.iter()
+        .find(|(_, n, ..)| n.eq_ignore_ascii_case(name))
+        .map(|(key, ..)| *key)
+}
+
+pub fn key_label(key: Key) -> String {
+    if let Key::Unknown(code) = key {
+        #[cfg(windows)]
+        if let Some((_, label)) = EXTRA_LABELS.iter().find(|(c, _)| *c == code) {
+            return label.to_string();
+        }
+
+        return format!("Key {}", code);
+    }
+
+    KEYS.iter()
+        .find(|(k, ..)| *k == key)
+        .map(|(_, _, label, _)| label.to_string())
+        .unwrap_or_else(|| format!("{:?}", key))
+}
+
+// Only the portal names keys by keysym
+#[cfg(target_os = "linux")]
+pub fn key_to_gdk(key: Key) -> Option<gdk::Key> {
+    KEYS.iter()
+        .find(|(k, ..)| *k == key)
+        .and_then(|(.., keyvals)| keyvals.first().copied())
 }
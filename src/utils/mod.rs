@@ -1,3 +1,3 @@
-pub mod random;
 pub mod bpm;
 pub mod keys;
+pub mod clock;